@@ -0,0 +1,114 @@
+use cube::sculpt::{DistanceField, SculptMode};
+use cube::space::{Axis, LogWidth, Partition};
+use cube::tree::{Cube, Node};
+use math::{self, FPoint3, FScalar, FVector3};
+use resource::ResourceId;
+
+/// The shape of a `Brush`.
+#[derive(Clone, Copy)]
+pub enum Shape {
+    /// A sphere.
+    Sphere,
+    /// A capped cylinder with its height along the given `Axis`.
+    Cylinder(Axis),
+    /// A capped cone with its apex in the positive direction along the given
+    /// `Axis`.
+    Cone(Axis),
+    /// A half-space behind a plane through the center of the `Brush` with the
+    /// given normal.
+    Plane(FVector3),
+}
+
+/// A brush used to sculpt cubes in a tree.
+///
+/// Brushes are bounded by a cube centered at their center that extends by
+/// their radius along each axis. Cylinders and cones have a height of twice
+/// their radius, and the base of a cone has the same radius as the `Brush`.
+#[derive(Clone, Copy)]
+pub struct Brush {
+    pub shape: Shape,
+    pub center: FPoint3,
+    pub radius: FScalar,
+}
+
+impl Brush {
+    pub fn new(shape: Shape, center: &FPoint3, radius: FScalar) -> Self {
+        Brush {
+            shape: shape,
+            center: *center,
+            radius: radius,
+        }
+    }
+
+    pub fn sphere(center: &FPoint3, radius: FScalar) -> Self {
+        Brush::new(Shape::Sphere, center, radius)
+    }
+
+    pub fn cylinder(axis: Axis, center: &FPoint3, radius: FScalar) -> Self {
+        Brush::new(Shape::Cylinder(axis), center, radius)
+    }
+
+    pub fn cone(axis: Axis, center: &FPoint3, radius: FScalar) -> Self {
+        Brush::new(Shape::Cone(axis), center, radius)
+    }
+
+    pub fn plane(normal: &FVector3, center: &FPoint3, radius: FScalar) -> Self {
+        Brush::new(Shape::Plane(normal.normalize()), center, radius)
+    }
+
+    /// Applies the `Brush` to a cube, subdividing cubes along the surface of
+    /// the `Brush` to the given width. See `Cube::sculpt`.
+    ///
+    /// Returns the `Partition`s of the affected cubes.
+    pub fn apply<N>(
+        &self,
+        cube: &mut Cube<N>,
+        width: LogWidth,
+        mode: SculptMode,
+        material: ResourceId,
+    ) -> Vec<Partition>
+    where
+        N: AsRef<Node> + AsMut<Node>,
+    {
+        cube.sculpt(self, width, mode, material)
+    }
+
+    /// Gets the distance from a point to the bounding cube of the `Brush`.
+    fn bounds_distance(&self, point: &FVector3) -> FScalar {
+        let distance = math::partial_max(point.x.abs(), point.y.abs());
+        math::partial_max(distance, point.z.abs()) - self.radius
+    }
+}
+
+impl DistanceField for Brush {
+    fn distance(&self, point: &FPoint3) -> FScalar {
+        let point = *point - self.center;
+        match self.shape {
+            Shape::Sphere => point.norm() - self.radius,
+            Shape::Cylinder(axis) => {
+                let (height, radial) = decompose(&point, axis);
+                math::partial_max(radial - self.radius, height.abs() - self.radius)
+            }
+            Shape::Cone(axis) => {
+                // The radius of the cone decreases linearly from its base to
+                // its apex. The slope of its side is one half, so distances
+                // from the side are scaled by the cosine of that slope.
+                let (height, radial) = decompose(&point, axis);
+                let cosine = 2.0 / (5.0 as FScalar).sqrt();
+                let side = (radial - ((self.radius - height) * 0.5)) * cosine;
+                math::partial_max(side, height.abs() - self.radius)
+            }
+            Shape::Plane(ref normal) => {
+                math::partial_max(point.dot(normal), self.bounds_distance(&point))
+            }
+        }
+    }
+}
+
+/// Decomposes a vector into its component along an `Axis` and its distance
+/// from that `Axis`.
+fn decompose(vector: &FVector3, axis: Axis) -> (FScalar, FScalar) {
+    let height = vector[axis as usize];
+    let radial = (*vector - (axis.to_vector() * height)).norm();
+    (height, radial)
+}
@@ -0,0 +1,204 @@
+use plexus::buffer::MeshBuffer;
+
+use cube::mesh::MeshOptions;
+use cube::space::{Intersects, LogWidth, Partition, Spatial, AABB};
+use cube::tree::{Cube, Node, Tree};
+use render::{Index, Vertex};
+
+/// A cache of meshes for the chunks of a `Tree`.
+///
+/// A chunk is a cube with a fixed width or a leaf that is wider than that
+/// width. Each chunk has its own `MeshBuffer`. When the tree is modified, only
+/// the chunks that were touched (and their neighbors, which may expose or
+/// hide faces) are rebuilt.
+///
+/// # Examples
+///
+/// Rebuilding only the chunks affected by an edit:
+///
+/// ```
+/// use bismuth::cube::{Cursor, Edit, LogWidth, MeshCache, MeshOptions, Tree};
+/// use bismuth::math::UPoint3;
+///
+/// let mut tree = Tree::new(LogWidth::new(8));
+/// let mut cache = MeshCache::new(&mut tree, LogWidth::new(6), MeshOptions::default());
+///
+/// let cursor = Cursor::at_point(&UPoint3::origin(), LogWidth::new(4));
+/// tree.as_cube_mut().apply(&cursor, &Edit::Clear);
+/// cache.update(&mut tree);
+/// ```
+pub struct MeshCache {
+    width: LogWidth,
+    options: MeshOptions,
+    chunks: Vec<(Partition, MeshBuffer<Index, Vertex>)>,
+}
+
+impl MeshCache {
+    /// Constructs a new `MeshCache` for a `Tree` with chunks of the given
+    /// width and builds the meshes of all chunks.
+    pub fn new(tree: &mut Tree, width: LogWidth, options: MeshOptions) -> Self {
+        let _ = tree.take_touched();
+        let mut cache = MeshCache {
+            width: width,
+            options: options,
+            chunks: vec![],
+        };
+        let aabb = tree.partition().aabb();
+        cache.rebuild(tree, &[aabb]);
+        cache
+    }
+
+    pub fn width(&self) -> LogWidth {
+        self.width
+    }
+
+    /// Rebuilds the meshes of chunks that have been touched since the last
+    /// update.
+    ///
+    /// Returns the number of chunks that were rebuilt.
+    pub fn update(&mut self, tree: &mut Tree) -> usize {
+        // Touched cubes are not widened to their chunk, so that only the
+        // neighboring chunks that they are adjacent to are rebuilt.
+        let regions = tree.take_touched()
+            .iter()
+            .map(|partition| partition.aabb())
+            .collect::<Vec<_>>();
+        if regions.is_empty() {
+            0
+        }
+        else {
+            self.rebuild(tree, &regions)
+        }
+    }
+
+    /// Gets the `MeshBuffer`s of all non-empty chunks.
+    pub fn mesh_buffers(&self) -> Vec<&MeshBuffer<Index, Vertex>> {
+        self.chunks
+            .iter()
+            .map(|&(_, ref buffer)| buffer)
+            .filter(|buffer| !buffer.as_index_slice().is_empty())
+            .collect()
+    }
+
+    /// Rebuilds the meshes of all chunks that intersect or are adjacent to
+    /// any of the given regions.
+    ///
+    /// The extents of `AABB`s are inclusive, so chunks that share a face with
+    /// a region do not intersect it. Regions are expanded by one unit, so that
+    /// adjacent chunks (whose faces may be exposed or hidden) are rebuilt.
+    fn rebuild(&mut self, tree: &Tree, regions: &[AABB]) -> usize {
+        let regions = regions.iter().map(expand).collect::<Vec<_>>();
+        let intersects = |aabb: &AABB| regions.iter().any(|region| region.intersects(aabb));
+        self.chunks
+            .retain(|&(ref partition, _)| !intersects(&partition.aabb()));
+        let root = tree.as_cube();
+        let options = &self.options;
+        let chunks = &mut self.chunks;
+        let mut count = 0;
+        for_each_chunk(&root, self.width, &intersects, &mut |cube| {
+            let buffer = cube.to_mesh_buffer_within(&root, options);
+            chunks.push((*cube.partition(), buffer));
+            count += 1;
+        });
+        count
+    }
+}
+
+/// Expands an `AABB` by one unit in each direction along each axis.
+fn expand(aabb: &AABB) -> AABB {
+    let (mut origin, mut extent) = (aabb.origin, aabb.extent);
+    for axis in 0..3 {
+        let lower = origin[axis].saturating_sub(1);
+        extent[axis] += (origin[axis] - lower) + 1;
+        origin[axis] = lower;
+    }
+    AABB::new(origin, extent)
+}
+
+/// Visits the chunks in a cube that satisfy a predicate. Sub-trees that do
+/// not satisfy the predicate are not visited.
+fn for_each_chunk<P, F>(cube: &Cube<&Node>, width: LogWidth, predicate: &P, f: &mut F)
+where
+    P: Fn(&AABB) -> bool,
+    F: FnMut(&Cube<&Node>),
+{
+    if !predicate(&cube.partition().aabb()) {
+        return;
+    }
+    if cube.is_leaf() || cube.partition().width() <= width {
+        f(cube);
+    }
+    else {
+        for index in 0..8 {
+            if let Some(cube) = cube.at_index(index) {
+                for_each_chunk(&cube, width, predicate, f);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cube::{Cursor, Edit, Geometry};
+    use math::UPoint3;
+
+    use super::*;
+
+    /// Gets the number of indices in the chunk at the given point.
+    fn chunk_len(cache: &MeshCache, point: &UPoint3) -> usize {
+        cache
+            .chunks
+            .iter()
+            .find(|&&(ref partition, _)| partition.origin() == point)
+            .map_or(0, |&(_, ref buffer)| buffer.as_index_slice().len())
+    }
+
+    #[test]
+    fn update_rebuilds_adjacent_chunks() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut()
+            .subdivide_to_point(&UPoint3::new(127, 100, 100), LogWidth::new(4));
+        let mut cache = MeshCache::new(&mut tree, LogWidth::new(6), MeshOptions::default());
+        assert_eq!(0, cache.update(&mut tree));
+
+        // Clear a leaf on the boundary between the chunk at (64, 64, 64) and
+        // the leaf chunk at (128, 0, 0), which exposes a face of the latter.
+        let hidden = chunk_len(&cache, &UPoint3::new(128, 0, 0));
+        tree.as_cube_mut()
+            .at_point_mut(&UPoint3::new(127, 100, 100), LogWidth::new(4))
+            .unwrap()
+            .as_leaf_mut()
+            .unwrap()
+            .geometry = Geometry::empty();
+
+        assert_eq!(2, cache.update(&mut tree));
+        assert!(chunk_len(&cache, &UPoint3::new(128, 0, 0)) > hidden);
+    }
+
+    #[test]
+    fn update_matches_rebuild() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut()
+            .subdivide_to_point(&UPoint3::origin(), LogWidth::new(6));
+        let mut cache = MeshCache::new(&mut tree, LogWidth::new(6), MeshOptions::default());
+        let cursor = Cursor::at_point(&UPoint3::new(60, 0, 60), LogWidth::new(3));
+        let _ = tree.as_cube_mut().apply(&cursor, &Edit::Clear);
+
+        // Only the edited chunk and the chunks adjacent to it are rebuilt.
+        let count = cache.update(&mut tree);
+        assert_eq!(8, count);
+
+        let len = |cache: &MeshCache| {
+            cache
+                .mesh_buffers()
+                .iter()
+                .map(|buffer| buffer.as_index_slice().len())
+                .sum::<usize>()
+        };
+        let rebuilt = MeshCache::new(&mut tree, LogWidth::new(6), MeshOptions::default());
+        assert_eq!(len(&rebuilt), len(&cache));
+        assert_eq!(rebuilt.chunks.len(), cache.chunks.len());
+        assert!(count < rebuilt.chunks.len());
+        assert!(tree.take_touched().is_empty());
+    }
+}
@@ -0,0 +1,352 @@
+use cube::geometry::Geometry;
+use cube::space::{Partition, Spatial};
+use cube::tree::{Cube, Node, Tree};
+use cube::CubeError;
+use math::UPoint3;
+use resource::ResourceId;
+
+/// A constructive solid geometry operation between cubes.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Operation {
+    /// Keeps the volume occupied by either cube.
+    Union,
+    /// Keeps the volume occupied by both cubes.
+    Intersection,
+    /// Keeps the volume occupied by the first cube but not the second.
+    Difference,
+}
+
+impl Operation {
+    fn apply(&self, a: &Geometry, b: &Geometry) -> Geometry {
+        match *self {
+            Operation::Union => a.union(b),
+            Operation::Intersection => a.intersection(b),
+            Operation::Difference => a.difference(b),
+        }
+    }
+}
+
+/// The right-hand side of an `Operation`.
+///
+/// Leaves that are combined with finer branches are subdivided on the fly,
+/// so operands are either cubes in a tree or virtual leaves.
+enum Operand<'a, 'b> {
+    Cube(Cube<'a, &'b Node>),
+    Leaf(Geometry, ResourceId),
+}
+
+impl<'a, 'b> Operand<'a, 'b> {
+    fn new(cube: Cube<'a, &'b Node>) -> Self {
+        let leaf = cube.as_leaf().map(|leaf| (leaf.geometry, leaf.material));
+        if let Some((geometry, material)) = leaf {
+            Operand::Leaf(geometry, material)
+        }
+        else {
+            Operand::Cube(cube)
+        }
+    }
+
+    fn at_index(&self, index: usize) -> Operand {
+        match *self {
+            Operand::Cube(ref cube) => Operand::new(cube.at_index(index).unwrap()),
+            Operand::Leaf(ref geometry, material) => {
+                Operand::Leaf(geometry.subdivision(index), material)
+            }
+        }
+    }
+}
+
+impl<'a, N> Cube<'a, N>
+where
+    N: AsRef<Node> + AsMut<Node>,
+{
+    /// Combines the cube with another cube using an `Operation`.
+    ///
+    /// The cubes are combined node by node. Where the resolutions of the cubes
+    /// differ, leaves are subdivided and the `Geometry` of leaves is combined
+    /// where possible. When forming a union, empty leaves take the material of
+    /// the other cube.
+    ///
+    /// Fails if the width of the other cube differs from the width of the
+    /// cube.
+    pub fn combine<M>(&mut self, other: &Cube<M>, operation: Operation) -> Result<(), CubeError>
+    where
+        M: AsRef<Node>,
+    {
+        if self.partition().width() == other.partition().width() {
+            combine(self, &Operand::new(other.with_node_ref()), operation);
+            Ok(())
+        }
+        else {
+            Err(CubeError::WidthMismatch)
+        }
+    }
+}
+
+impl Tree {
+    /// Combines the tree with another tree placed at the given point using an
+    /// `Operation`. The point is aligned to the width of the other tree.
+    ///
+    /// Fails if the other tree does not fit within the tree at the given
+    /// point.
+    pub fn combine_at(
+        &mut self,
+        other: &Tree,
+        point: &UPoint3,
+        operation: Operation,
+    ) -> Result<(), CubeError> {
+        let point = *Partition::at_point(point, other.partition().width()).origin();
+        let mut cube = self.as_cube_mut();
+        let mut cube = cube
+            .subdivide_to_point(&point, other.partition().width())
+            .ok_or(CubeError::OutOfBounds)?;
+        cube.combine(&other.as_cube(), operation)
+    }
+}
+
+fn combine<N>(cube: &mut Cube<N>, operand: &Operand, operation: Operation)
+where
+    N: AsRef<Node> + AsMut<Node>,
+{
+    match *operand {
+        Operand::Leaf(ref geometry, material) => {
+            if cube.is_leaf() {
+                if let Some(leaf) = cube.as_leaf_mut() {
+                    if operation == Operation::Union && leaf.geometry.is_empty() {
+                        leaf.material = material;
+                    }
+                    leaf.geometry = operation.apply(&leaf.geometry, geometry);
+                }
+            }
+            else if geometry.is_empty() || geometry.is_full() {
+                // Combining a branch with an empty or full leaf either leaves
+                // the branch unchanged or replaces it with that leaf.
+                let replace = match operation {
+                    Operation::Union => geometry.is_full(),
+                    Operation::Intersection => geometry.is_empty(),
+                    Operation::Difference => geometry.is_full(),
+                };
+                if replace {
+                    let _ = cube.join();
+                    if let Some(leaf) = cube.as_leaf_mut() {
+                        leaf.geometry = operation.apply(&Geometry::full(), geometry);
+                        leaf.material = material;
+                    }
+                }
+            }
+            else {
+                combine_subdivisions(cube, operand, operation);
+            }
+        }
+        Operand::Cube(..) => {
+            let leaf = cube.as_leaf().map(|leaf| (leaf.geometry, leaf.material));
+            if let Some((geometry, material)) = leaf {
+                let unaffected = match operation {
+                    Operation::Union => geometry.is_full(),
+                    Operation::Intersection | Operation::Difference => geometry.is_empty(),
+                };
+                if unaffected {
+                    return;
+                }
+                if cube.subdivide().is_ok() {
+                    for index in 0..8 {
+                        if let Some(mut cube) = cube.at_index_mut(index) {
+                            if let Some(leaf) = cube.as_leaf_mut() {
+                                leaf.geometry = geometry.subdivision(index);
+                                leaf.material = material;
+                            }
+                        }
+                    }
+                }
+            }
+            combine_subdivisions(cube, operand, operation);
+        }
+    }
+}
+
+fn combine_subdivisions<N>(cube: &mut Cube<N>, operand: &Operand, operation: Operation)
+where
+    N: AsRef<Node> + AsMut<Node>,
+{
+    for index in 0..8 {
+        if let Some(mut cube) = cube.at_index_mut(index) {
+            combine(&mut cube, &operand.at_index(index), operation);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cube::geometry::Offset;
+    use cube::space::{LogWidth, Orientation};
+
+    /// Sets the geometry and material of the leaf of a cube.
+    fn set_leaf<N>(cube: &mut Cube<N>, geometry: Geometry, material: ResourceId)
+    where
+        N: AsRef<Node> + AsMut<Node>,
+    {
+        let leaf = cube.as_leaf_mut().unwrap();
+        leaf.geometry = geometry;
+        leaf.material = material;
+    }
+
+    #[test]
+    fn difference_carves_placed_tree() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let stencil = Tree::new(LogWidth::new(6));
+        let point = UPoint3::new(64, 64, 64);
+        tree.combine_at(&stencil, &point, Operation::Difference).unwrap();
+
+        assert!(
+            tree.as_cube()
+                .at_point(&point, LogWidth::new(6))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            !tree.as_cube()
+                .at_point(&UPoint3::origin(), LogWidth::new(6))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn union_fills_empty_leaves_with_material() {
+        let mut tree = Tree::empty(LogWidth::new(8));
+        let mut stencil = Tree::new(LogWidth::new(6));
+        set_leaf(&mut stencil.as_cube_mut(), Geometry::full(), 3);
+        let point = UPoint3::new(64, 0, 64);
+        tree.combine_at(&stencil, &point, Operation::Union).unwrap();
+
+        let root = tree.as_cube();
+        let cube = root.at_point(&point, LogWidth::new(6)).unwrap();
+        let leaf = cube.as_leaf().unwrap();
+        assert!(leaf.geometry.is_full());
+        assert_eq!(3, leaf.material);
+        assert!(
+            root.at_point(&UPoint3::new(128, 128, 128), LogWidth::new(6))
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn intersection_keeps_shared_volume() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().subdivide().unwrap();
+        set_leaf(&mut tree.as_cube_mut().at_index_mut(0).unwrap(), Geometry::empty(), 0);
+        let mut other = Tree::new(LogWidth::new(8));
+        other.as_cube_mut().subdivide().unwrap();
+        set_leaf(&mut other.as_cube_mut().at_index_mut(1).unwrap(), Geometry::empty(), 0);
+        tree.as_cube_mut()
+            .combine(&other.as_cube(), Operation::Intersection)
+            .unwrap();
+
+        let root = tree.as_cube();
+        assert!(root.at_index(0).unwrap().is_empty());
+        assert!(root.at_index(1).unwrap().is_empty());
+        for index in 2..8 {
+            assert!(root.at_index(index).unwrap().as_leaf().unwrap().geometry.is_full());
+        }
+    }
+
+    #[test]
+    fn difference_subdivides_leaf_to_match_branch() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        set_leaf(&mut tree.as_cube_mut(), Geometry::full(), 2);
+        let mut stencil = Tree::empty(LogWidth::new(8));
+        set_leaf(
+            &mut stencil
+                .as_cube_mut()
+                .subdivide_to_point(&UPoint3::origin(), LogWidth::new(6))
+                .unwrap(),
+            Geometry::full(),
+            0,
+        );
+        tree.as_cube_mut()
+            .combine(&stencil.as_cube(), Operation::Difference)
+            .unwrap();
+
+        // The leaf is subdivided to the width of the carved cube, and the
+        // remaining cubes keep its material.
+        let root = tree.as_cube();
+        let carved = root.at_point(&UPoint3::origin(), LogWidth::new(6)).unwrap();
+        assert!(carved.partition().width() == LogWidth::new(6));
+        assert!(carved.is_empty());
+        let leaf = root.at_point(&UPoint3::new(64, 0, 0), LogWidth::new(6))
+            .unwrap();
+        let leaf = leaf.as_leaf().unwrap();
+        assert!(leaf.geometry.is_full());
+        assert_eq!(2, leaf.material);
+    }
+
+    #[test]
+    fn intersection_subdivides_deformed_leaf_to_match_branch() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().subdivide().unwrap();
+        let mut other = Tree::new(LogWidth::new(8));
+        let mut geometry = Geometry::full();
+        geometry.push_face(Orientation::Top, Offset::from(8));
+        set_leaf(&mut other.as_cube_mut(), geometry, 0);
+        tree.as_cube_mut()
+            .combine(&other.as_cube(), Operation::Intersection)
+            .unwrap();
+
+        // The top face lies below the midpoint, so the upper subdivisions are
+        // empty and the lower subdivisions are deformed.
+        let root = tree.as_cube();
+        for index in 0..8 {
+            let cube = root.at_index(index).unwrap();
+            let geometry = cube.as_leaf().unwrap().geometry;
+            if index & 2 == 0 {
+                assert!(!geometry.is_empty() && !geometry.is_full());
+            }
+            else {
+                assert!(geometry.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn union_with_full_leaf_replaces_branch() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().subdivide().unwrap();
+        set_leaf(&mut tree.as_cube_mut().at_index_mut(3).unwrap(), Geometry::empty(), 0);
+        let mut other = Tree::new(LogWidth::new(8));
+        set_leaf(&mut other.as_cube_mut(), Geometry::full(), 5);
+        tree.as_cube_mut()
+            .combine(&other.as_cube(), Operation::Union)
+            .unwrap();
+
+        let root = tree.as_cube();
+        let leaf = root.as_leaf().unwrap();
+        assert!(leaf.geometry.is_full());
+        assert_eq!(5, leaf.material);
+    }
+
+    #[test]
+    fn combine_rejects_mismatched_widths() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let other = Tree::new(LogWidth::new(6));
+        let result = tree.as_cube_mut().combine(&other.as_cube(), Operation::Union);
+
+        assert!(match result {
+            Err(CubeError::WidthMismatch) => true,
+            _ => false,
+        });
+    }
+
+    #[test]
+    fn combine_at_rejects_points_out_of_bounds() {
+        let mut tree = Tree::new(LogWidth::new(6));
+        let other = Tree::new(LogWidth::new(4));
+        let result = tree.combine_at(&other, &UPoint3::new(128, 0, 0), Operation::Union);
+
+        assert!(match result {
+            Err(CubeError::OutOfBounds) => true,
+            _ => false,
+        });
+    }
+}
@@ -1,8 +1,29 @@
 use num::Zero;
 use std::cmp;
+use std::collections::BTreeMap;
 
-use cube::space::{LogWidth, Partition, Spatial, AABB};
-use math::{LowerBound, UPoint3, UVector3, UpperBound};
+use cube::geometry::Geometry;
+use cube::space::{Axis, LogWidth, Partition, Spatial, AABB};
+use cube::tree::{Cube, Node};
+use math::{FScalar, LowerBound, UPoint3, UVector3, UpperBound};
+use resource::ResourceId;
+
+/// An edit that can be applied to the cubes selected by a `Cursor`.
+#[derive(Clone, Copy)]
+pub enum Edit {
+    /// Fills cubes with full (undeformed) geometry.
+    Fill,
+    /// Clears cubes, leaving them empty.
+    Clear,
+    /// Sets the material of all leaves in the selected cubes.
+    SetMaterial(ResourceId),
+    /// Sets the geometry of cubes.
+    SetGeometry(Geometry),
+    /// Fills cubes on the faces of the selection and clears its interior.
+    Hollow,
+    /// Fills cubes on the edges of the selection and clears everything else.
+    Outline,
+}
 
 /// A contiguous selection of cubes in a tree.
 ///
@@ -104,4 +125,207 @@ impl Cursor {
     pub fn aabb(&self) -> AABB {
         AABB::new(self.origin, self.extent())
     }
+
+    /// Gets the number of axes along which a cube lies on the boundary of the
+    /// `Cursor`. Interior cubes have no boundary axes, cubes on the faces of
+    /// the selection have one, and cubes on its edges have two or more.
+    fn boundary_axes<C>(&self, cube: &C) -> usize
+    where
+        C: Spatial,
+    {
+        Axis::range()
+            .filter(|axis| {
+                let index = (cube.partition().origin()[*axis] - self.origin[*axis]) /
+                    self.width.exp();
+                index == 0 || index == self.span[*axis]
+            })
+            .count()
+    }
+}
+
+impl<'a, N> Cube<'a, N>
+where
+    N: AsRef<Node> + AsMut<Node>,
+{
+    /// Applies an `Edit` to the cubes selected by a `Cursor`, subdividing the
+    /// tree as needed. Returns the `Partition`s of the affected cubes, which
+    /// can be used to remesh or record an undo history.
+    pub fn apply(&mut self, cursor: &Cursor, edit: &Edit) -> Vec<Partition> {
+        self.subdivide_to_cursor(cursor)
+            .into_iter()
+            .map(|mut cube| {
+                match *edit {
+                    Edit::Fill => set_geometry(&mut cube, Geometry::full()),
+                    Edit::Clear => set_geometry(&mut cube, Geometry::empty()),
+                    Edit::SetMaterial(material) => {
+                        for mut cube in cube.iter_mut() {
+                            if let Some(leaf) = cube.as_leaf_mut() {
+                                leaf.material = material;
+                            }
+                        }
+                    }
+                    Edit::SetGeometry(geometry) => set_geometry(&mut cube, geometry),
+                    Edit::Hollow => {
+                        let geometry = if cursor.boundary_axes(&cube) > 0 {
+                            Geometry::full()
+                        }
+                        else {
+                            Geometry::empty()
+                        };
+                        set_geometry(&mut cube, geometry);
+                    }
+                    Edit::Outline => {
+                        let geometry = if cursor.boundary_axes(&cube) > 1 {
+                            Geometry::full()
+                        }
+                        else {
+                            Geometry::empty()
+                        };
+                        set_geometry(&mut cube, geometry);
+                    }
+                }
+                *cube.partition()
+            })
+            .collect()
+    }
+}
+
+/// Sets the geometry of a cube, joining it into a leaf if necessary.
+///
+/// When a branch is joined, the leaf takes the material that occupies the
+/// most volume among its non-empty leaves (or among all of its leaves if
+/// they are all empty).
+fn set_geometry(cube: &mut Cube<&mut Node>, geometry: Geometry) {
+    let material = majority_material(&cube.with_node_ref());
+    let _ = cube.join();
+    if let Some(leaf) = cube.as_leaf_mut() {
+        leaf.geometry = geometry;
+        leaf.material = material;
+    }
+}
+
+/// Gets the material that occupies the most volume among the non-empty
+/// leaves of a cube, falling back to the volume of all leaves if the cube is
+/// empty.
+fn majority_material(cube: &Cube<&Node>) -> ResourceId {
+    let mut volumes = BTreeMap::new();
+    for cube in cube.iter() {
+        if let Some(leaf) = cube.as_leaf() {
+            let width = cube.partition().width().exp() as FScalar;
+            let volume = width * width * width;
+            let entry = volumes.entry(leaf.material).or_insert((0.0, 0.0));
+            if leaf.geometry.is_empty() {
+                entry.1 += volume;
+            }
+            else {
+                entry.0 += volume;
+            }
+        }
+    }
+    let (material, _) = volumes.into_iter().fold(
+        (0, (0.0, 0.0)),
+        |(material, volume), (other, other_volume)| {
+            if other_volume > volume {
+                (other, other_volume)
+            }
+            else {
+                (material, volume)
+            }
+        },
+    );
+    material
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cube::Tree;
+
+    #[test]
+    fn hollow_clears_interior() {
+        let width = LogWidth::min_value();
+        let unit = width.exp();
+        let cursor = Cursor::at_point_with_span(&UPoint3::origin(), width, &UVector3::new(2, 2, 2));
+        let mut tree = Tree::new(LogWidth::new(8));
+        let partitions = tree.as_cube_mut().apply(&cursor, &Edit::Hollow);
+
+        assert_eq!(27, partitions.len());
+        assert!(
+            tree.as_cube()
+                .at_point(&UPoint3::new(unit, unit, unit), width)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            !tree.as_cube()
+                .at_point(&UPoint3::origin(), width)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn outline_clears_faces() {
+        let width = LogWidth::min_value();
+        let unit = width.exp();
+        let cursor = Cursor::at_point_with_span(&UPoint3::origin(), width, &UVector3::new(2, 2, 2));
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().apply(&cursor, &Edit::Outline);
+
+        assert!(
+            tree.as_cube()
+                .at_point(&UPoint3::new(unit, unit, 0), width)
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            !tree.as_cube()
+                .at_point(&UPoint3::new(unit, 0, 0), width)
+                .unwrap()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn fill_keeps_majority_material_of_joined_branch() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().subdivide().unwrap();
+        for index in 0..8 {
+            let mut cube = tree.as_cube_mut();
+            let mut cube = cube.at_index_mut(index).unwrap();
+            let leaf = cube.as_leaf_mut().unwrap();
+            if index < 2 {
+                leaf.material = 2;
+            }
+            else {
+                leaf.material = 4;
+                if index < 7 {
+                    leaf.geometry = Geometry::empty();
+                }
+            }
+        }
+        let cursor = Cursor::at_point(&UPoint3::origin(), LogWidth::new(8));
+        tree.as_cube_mut().apply(&cursor, &Edit::Fill);
+
+        // Material 4 covers more leaves, but most of them are empty.
+        let root = tree.as_cube();
+        let leaf = root.as_leaf().unwrap();
+        assert!(leaf.geometry.is_full());
+        assert_eq!(2, leaf.material);
+    }
+
+    #[test]
+    fn clear_keeps_material_of_painted_branch() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().subdivide().unwrap();
+        let cursor = Cursor::at_point(&UPoint3::origin(), LogWidth::new(8));
+        tree.as_cube_mut().apply(&cursor, &Edit::SetMaterial(3));
+        tree.as_cube_mut().apply(&cursor, &Edit::Clear);
+        tree.as_cube_mut().apply(&cursor, &Edit::Fill);
+
+        let root = tree.as_cube();
+        let leaf = root.as_leaf().unwrap();
+        assert!(leaf.geometry.is_full());
+        assert_eq!(3, leaf.material);
+    }
 }
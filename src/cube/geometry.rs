@@ -1,6 +1,8 @@
+use std::cmp;
+
 use clamp::{Clamped, ClampedRange};
-use cube::space::Axis;
-use math::{Clamp, FPoint3, FScalar, FromSpace, UPoint3};
+use cube::space::{self, Axis, Direction, Orientation};
+use math::{self, Clamp, FPoint3, FScalar, FromSpace, UPoint3, UScalar};
 
 /// Defines the bounds for `Offset` values.
 #[derive(Clone, Copy)]
@@ -34,10 +36,42 @@ pub type Offset = Clamped<u8, OffsetRange>;
 ///
 /// The front and back of an `Edge` cannot cross, but may intersect. These
 /// values are packed into the upper and lower 4-bit halves of an `u8`.
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq)]
 pub struct Edge(u8);
 
 impl Edge {
+    /// Constructs a new `Edge` with the given front and back. If the back would
+    /// cross the front, it will be clamped such that it intersects the front.
+    pub fn new(front: Offset, back: Offset) -> Self {
+        let back = back.clamp(front, Offset::max_value());
+        Edge((front.to_inner() << 4) | back.to_inner())
+    }
+
+    /// Constructs a new `Edge` spanning the interval along which a signed
+    /// distance is negative, where the distance is linearly interpolated from
+    /// its values at the front and back. If the distance is never negative,
+    /// then the `Edge` converges at the endpoint nearest to the surface.
+    pub fn from_distances(front: FScalar, back: FScalar) -> Self {
+        let crossing = || {
+            let max = Offset::max_inner_value() as FScalar;
+            Offset::from(((front / (front - back)) * max).round() as u8)
+        };
+        match (front < 0.0, back < 0.0) {
+            (true, true) => Edge::full(),
+            (true, false) => Edge::new(Offset::min_value(), crossing()),
+            (false, true) => Edge::new(crossing(), Offset::max_value()),
+            (false, false) => {
+                let offset = if front <= back {
+                    Offset::min_value()
+                }
+                else {
+                    Offset::max_value()
+                };
+                Edge::converged(offset)
+            }
+        }
+    }
+
     /// Constructs a new `Edge` that spans the full width (no deformation).
     fn full() -> Self {
         Edge(Offset::max_inner_value())
@@ -78,6 +112,47 @@ impl Edge {
         self.back() - self.front()
     }
 
+    /// Gets the union of two `Edge`s. Because an `Edge` is contiguous, this is
+    /// the smallest `Edge` that spans both `Edge`s.
+    pub fn union(&self, other: &Self) -> Self {
+        Edge::new(
+            cmp::min(self.front(), other.front()),
+            cmp::max(self.back(), other.back()),
+        )
+    }
+
+    /// Gets the difference of two `Edge`s. If the other `Edge` would split the
+    /// `Edge` in two, then the longest remaining part is used.
+    pub fn difference(&self, other: &Self) -> Self {
+        if other.length() == 0 || other.back() <= self.front() || other.front() >= self.back() {
+            *self
+        }
+        else if other.front() <= self.front() && other.back() >= self.back() {
+            Edge::converged(self.front())
+        }
+        else if other.front() <= self.front() {
+            Edge::new(other.back(), self.back())
+        }
+        else if other.back() >= self.back() {
+            Edge::new(self.front(), other.front())
+        }
+        else if (other.front() - self.front()) >= (self.back() - other.back()) {
+            Edge::new(self.front(), other.front())
+        }
+        else {
+            Edge::new(other.back(), self.back())
+        }
+    }
+
+    /// Gets the intersection of two `Edge`s. If the `Edge`s do not overlap,
+    /// then the intersection converges at the front of the rearmost `Edge`.
+    pub fn intersection(&self, other: &Self) -> Self {
+        Edge::new(
+            cmp::max(self.front(), other.front()),
+            cmp::min(self.back(), other.back()),
+        )
+    }
+
     fn front_unit_transform(&self) -> FScalar {
         let min = Offset::min_inner_value();
         let n = (self.front().to_inner() - min) as FScalar;
@@ -94,7 +169,7 @@ impl Edge {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Eq, PartialEq)]
 pub struct Geometry([[Edge; 4]; 3]);
 
 impl Geometry {
@@ -106,6 +181,23 @@ impl Geometry {
         Geometry([[Edge::converged(Offset::from(0)); 4]; 3])
     }
 
+    /// Constructs a `Geometry` from a function that computes each `Edge` from
+    /// its axis and the points of the unit cube at its front and back,
+    /// respectively.
+    pub(super) fn from_edges<F>(mut f: F) -> Self
+    where
+        F: FnMut(usize, &UPoint3, &UPoint3) -> Edge,
+    {
+        let mut geometry = Geometry::full();
+        for axis in Axis::range() {
+            for index in 0..4 {
+                let (front, back) = edge_endpoints(axis, index);
+                geometry.0[axis][index] = f(axis, &front, &back);
+            }
+        }
+        geometry
+    }
+
     pub fn edges(&self, axis: Axis) -> &[Edge; 4] {
         &self.0[axis as usize]
     }
@@ -114,12 +206,134 @@ impl Geometry {
         &mut self.0[axis as usize]
     }
 
+    /// Pushes a face into the cube by the given `Offset`.
+    ///
+    /// The face cannot cross its opposing face. If it would, it converges
+    /// with the opposing face instead.
+    pub fn push_face(&mut self, orientation: Orientation, offset: Offset) {
+        let offset = offset.to_inner();
+        self.map_face(orientation, |edge, front| {
+            if front {
+                let front = edge.front().to_inner() + offset;
+                edge.set_front(front.into());
+            }
+            else {
+                let back = edge.back().to_inner().saturating_sub(offset);
+                edge.set_back(back.into());
+            }
+        });
+    }
+
+    /// Pulls a face out of the cube by the given `Offset`.
+    ///
+    /// The face cannot extend beyond the bounds of the undeformed cube.
+    pub fn pull_face(&mut self, orientation: Orientation, offset: Offset) {
+        let offset = offset.to_inner();
+        self.map_face(orientation, |edge, front| {
+            if front {
+                let front = edge.front().to_inner().saturating_sub(offset);
+                edge.set_front(front.into());
+            }
+            else {
+                let back = edge.back().to_inner() + offset;
+                edge.set_back(back.into());
+            }
+        });
+    }
+
+    /// Sets the `Offset`s of the corners of a face.
+    ///
+    /// `Offset`s are absolute coordinates along the axis of the face and are
+    /// ordered like the `Edge`s along that axis. The corners of a face cannot
+    /// cross the corners of its opposing face.
+    pub fn set_face_offsets(&mut self, orientation: Orientation, offsets: [Offset; 4]) {
+        let mut offsets = offsets.iter();
+        self.map_face(orientation, |edge, front| {
+            if let Some(offset) = offsets.next() {
+                if front {
+                    edge.set_front(*offset);
+                }
+                else {
+                    edge.set_back(*offset);
+                }
+            }
+        });
+    }
+
+    /// Moves a corner of the cube to the given `Offset`s along each axis.
+    ///
+    /// Corners are indexed like the subdivisions of a cube. A corner cannot
+    /// cross the corners opposite to it along each axis.
+    pub fn move_corner(&mut self, index: usize, offsets: [Offset; 3]) {
+        let unit = space::unit_at_index(index);
+        for axis in Axis::range() {
+            let edge = &mut self.0[axis][index_at_axis(axis, &unit)];
+            if unit[axis] == 0 {
+                edge.set_front(offsets[axis]);
+            }
+            else {
+                edge.set_back(offsets[axis]);
+            }
+        }
+    }
+
+    /// Applies a function to each `Edge` along the axis of a face. The
+    /// function also receives whether the face lies at the front of the
+    /// `Edge`s.
+    fn map_face<F>(&mut self, orientation: Orientation, mut f: F)
+    where
+        F: FnMut(&mut Edge, bool),
+    {
+        let front = orientation.direction() == Direction::Positive;
+        for edge in self.edges_mut(orientation.axis()) {
+            f(edge, front);
+        }
+    }
+
     pub fn is_empty(&self) -> bool {
         self.0
             .iter()
             .any(|axis| axis.iter().all(|edge| edge.length() == 0))
     }
 
+    /// Returns `true` if the `Geometry` has no deformation.
+    pub fn is_full(&self) -> bool {
+        self.0
+            .iter()
+            .all(|axis| axis.iter().all(|edge| *edge == Edge::full()))
+    }
+
+    /// Gets the union of two `Geometry`s.
+    ///
+    /// Because the result must be a single deformed cube, this is
+    /// approximated as the union of each pair of `Edge`s, which may
+    /// overestimate the union of the shapes.
+    pub fn union(&self, other: &Self) -> Self {
+        if self.is_empty() {
+            *other
+        }
+        else if other.is_empty() {
+            *self
+        }
+        else {
+            self.zip_edges(other, |a, b| a.union(b))
+        }
+    }
+
+    /// Gets the intersection of two `Geometry`s.
+    ///
+    /// This is approximated as the intersection of each pair of `Edge`s. If
+    /// the intersection is empty, then `Geometry::empty` is returned.
+    pub fn intersection(&self, other: &Self) -> Self {
+        let geometry = self.zip_edges(other, |a, b| a.intersection(b));
+        if geometry.is_empty() {
+            Geometry::empty()
+        }
+        else {
+            geometry
+        }
+    }
+
     pub fn map_unit_cube_point(&self, unit: &UPoint3) -> FPoint3 {
         let mut point = FPoint3::from_space(*unit);
         for axis in Axis::range() {
@@ -133,12 +347,326 @@ impl Geometry {
         }
         point
     }
+
+    /// Determines if a point in the unit cube lies within the deformed cube.
+    ///
+    /// The bounds of the deformed cube along each axis are bilinearly
+    /// interpolated from the `Edge`s along that axis at the coordinates of the
+    /// point along the other axes.
+    pub fn contains_unit_point(&self, point: &FPoint3) -> bool {
+        if self.is_empty() {
+            return false;
+        }
+        Axis::range().all(|axis| {
+            let (front, back) = self.unit_bounds(axis, point);
+            point[axis] >= front && point[axis] <= back
+        })
+    }
+
+    /// Gets the front and back bounds of the deformed cube along an axis at
+    /// the coordinates of a point in the unit cube along the other axes.
+    ///
+    /// Bounds are bilinearly interpolated from the `Edge`s along the axis.
+    pub(super) fn unit_bounds(&self, axis: usize, point: &FPoint3) -> (FScalar, FScalar) {
+        let (p, q) = orthogonal_axes(axis);
+        let mut fronts = [0.0; 4];
+        let mut backs = [0.0; 4];
+        for (index, edge) in self.0[axis].iter().enumerate() {
+            fronts[index] = edge.front_unit_transform();
+            backs[index] = 1.0 + edge.back_unit_transform();
+        }
+        (
+            bilerp(&fronts, point[p], point[q]),
+            bilerp(&backs, point[p], point[q]),
+        )
+    }
+
+    /// Rotates the `Geometry` by the given number of positive (right-handed)
+    /// quarter turns about an `Axis` through the midpoint of the cube.
+    /// Negative turns rotate in the opposite direction.
+    pub fn rotate(&self, axis: Axis, quarter_turns: i32) -> Self {
+        let mut geometry = *self;
+        for _ in 0..math::umod(quarter_turns, 4) {
+            geometry =
+                geometry.transform(|point, max| space::rotate_lattice_point(point, axis, max));
+        }
+        geometry
+    }
+
+    /// Reflects the `Geometry` across the plane through the midpoint of the
+    /// cube that is orthogonal to the given `Axis`.
+    pub fn reflect(&self, axis: Axis) -> Self {
+        self.transform(|point, max| space::reflect_lattice_point(point, axis, max))
+    }
+
+    /// Transforms the `Geometry` by an isometry of the unit cube onto itself,
+    /// such as a rotation or reflection.
+    ///
+    /// The transformation `f` maps a point in a cubic lattice spanning
+    /// `[0, max]` along each axis to another point in that lattice. It is used
+    /// both to permute the corners of the unit cube and to map the deformed
+    /// positions of those corners.
+    pub(super) fn transform<F>(&self, f: F) -> Self
+    where
+        F: Fn(&UPoint3, UScalar) -> UPoint3,
+    {
+        let max = Offset::max_inner_value() as UScalar;
+        let mut fronts = [[Offset::min_value(); 4]; 3];
+        let mut backs = [[Offset::max_value(); 4]; 3];
+        for index in 0..8 {
+            let unit = space::unit_at_index(index);
+            let corner = f(&self.corner(&unit), max);
+            let unit = f(&unit, 1);
+            for axis in Axis::range() {
+                let offset = Offset::from(corner[axis] as u8);
+                if unit[axis] == 0 {
+                    fronts[axis][index_at_axis(axis, &unit)] = offset;
+                }
+                else {
+                    backs[axis][index_at_axis(axis, &unit)] = offset;
+                }
+            }
+        }
+        let mut geometry = Geometry::full();
+        for axis in Axis::range() {
+            for index in 0..4 {
+                geometry.0[axis][index] = Edge::new(fronts[axis][index], backs[axis][index]);
+            }
+        }
+        geometry
+    }
+
+    /// Gets the difference of two `Geometry`s.
+    ///
+    /// This is approximated as the difference of each pair of `Edge`s. If the
+    /// difference is empty, then `Geometry::empty` is returned.
+    pub fn difference(&self, other: &Self) -> Self {
+        if other.is_empty() {
+            *self
+        }
+        else if self.is_empty() || other.is_full() {
+            Geometry::empty()
+        }
+        else {
+            let geometry = self.zip_edges(other, |a, b| a.difference(b));
+            if geometry.is_empty() {
+                Geometry::empty()
+            }
+            else {
+                geometry
+            }
+        }
+    }
+
+    /// Gets the `Geometry` of the subdivision of a cube at the given index.
+    ///
+    /// The `Edge`s of the subdivision are interpolated from the `Edge`s of the
+    /// `Geometry` and clipped to the partition of the subdivision.
+    pub(super) fn subdivision(&self, index: usize) -> Self {
+        if self.is_empty() || self.is_full() {
+            return *self;
+        }
+        let octant = space::unit_at_index(index);
+        let max = Offset::max_inner_value() as FScalar;
+        let geometry = Geometry::from_edges(|axis, unit, _| {
+            let (p, q) = orthogonal_axes(axis);
+            let u = (octant[p] + unit[p]) as FScalar * 0.5;
+            let v = (octant[q] + unit[q]) as FScalar * 0.5;
+            let edges = &self.0[axis];
+            let mut fronts = [0.0; 4];
+            let mut backs = [0.0; 4];
+            for (corner, edge) in edges.iter().enumerate() {
+                fronts[corner] = edge.front().to_inner() as FScalar;
+                backs[corner] = edge.back().to_inner() as FScalar;
+            }
+            let lower = octant[axis] as FScalar * max * 0.5;
+            let upper = lower + (max * 0.5);
+            let front = bilerp(&fronts, u, v);
+            let back = bilerp(&backs, u, v);
+            let map = |offset: FScalar| Offset::from(((offset - lower) * 2.0).round() as u8);
+            if back < lower {
+                Edge::converged(Offset::min_value())
+            }
+            else if front > upper {
+                Edge::converged(Offset::max_value())
+            }
+            else {
+                Edge::new(
+                    map(math::partial_max(front, lower)),
+                    map(math::partial_min(back, upper)),
+                )
+            }
+        });
+        if geometry.is_empty() {
+            Geometry::empty()
+        }
+        else {
+            geometry
+        }
+    }
+
+    fn zip_edges<F>(&self, other: &Self, f: F) -> Self
+    where
+        F: Fn(&Edge, &Edge) -> Edge,
+    {
+        let mut geometry = *self;
+        for axis in Axis::range() {
+            for index in 0..4 {
+                geometry.0[axis][index] = f(&self.0[axis][index], &other.0[axis][index]);
+            }
+        }
+        geometry
+    }
+
+    /// Gets the deformed position of a corner of the unit cube as the `Offset`
+    /// along each axis.
+    fn corner(&self, unit: &UPoint3) -> UPoint3 {
+        let mut corner = UPoint3::origin();
+        for axis in Axis::range() {
+            let edge = &self.0[axis][index_at_axis(axis, unit)];
+            let offset = if unit[axis] == 0 {
+                edge.front()
+            }
+            else {
+                edge.back()
+            };
+            corner[axis] = offset.to_inner() as UScalar;
+        }
+        corner
+    }
 }
 
 /// Gets the index of an `Edge` in a face (a collection of four `Edge`s along a
 /// particular axis).
 fn index_at_axis(axis: usize, unit: &UPoint3) -> usize {
+    let (p, q) = orthogonal_axes(axis);
+    (unit[p] | (unit[q] << 1)) as usize
+}
+
+/// Gets the points of the unit cube at the front and back of the `Edge` at the
+/// given index along a particular axis. This is the inverse of
+/// `index_at_axis`.
+fn edge_endpoints(axis: usize, index: usize) -> (UPoint3, UPoint3) {
+    let (p, q) = orthogonal_axes(axis);
+    let mut front = UPoint3::origin();
+    front[p] = (index & 1) as UScalar;
+    front[q] = ((index >> 1) & 1) as UScalar;
+    let mut back = front;
+    back[axis] = 1;
+    (front, back)
+}
+
+/// Bilinearly interpolates values at the corners of a face indexed like the
+/// `Edge`s along an axis.
+fn bilerp(values: &[FScalar; 4], u: FScalar, v: FScalar) -> FScalar {
+    let a = (values[0] * (1.0 - u)) + (values[1] * u);
+    let b = (values[2] * (1.0 - u)) + (values[3] * u);
+    (a * (1.0 - v)) + (b * v)
+}
+
+/// Gets the axes orthogonal to the given axis, in the order used to index
+/// `Edge`s.
+fn orthogonal_axes(axis: usize) -> (usize, usize) {
     let p = if axis == 0 { 1 } else { 0 };
     let q = if axis == 2 { 1 } else { 2 };
-    (unit[p] | (unit[q] << 1)) as usize
+    (p, q)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_union_and_intersection() {
+        let a = Edge::new(Offset::from(2), Offset::from(10));
+        let b = Edge::new(Offset::from(5), Offset::from(12));
+
+        let union = a.union(&b);
+        assert_eq!(2, union.front().to_inner());
+        assert_eq!(12, union.back().to_inner());
+        let intersection = a.intersection(&b);
+        assert_eq!(5, intersection.front().to_inner());
+        assert_eq!(10, intersection.back().to_inner());
+        // Disjoint edges converge rather than crossing.
+        let c = Edge::new(Offset::from(12), Offset::from(14));
+        assert_eq!(0, a.intersection(&c).length().to_inner());
+    }
+
+    #[test]
+    fn geometry_union_and_intersection() {
+        let mut left = Geometry::full();
+        left.push_face(Orientation::Right, Offset::from(8));
+        let mut right = Geometry::full();
+        right.push_face(Orientation::Left, Offset::from(8));
+
+        assert!(left.union(&right).is_full());
+        assert!(left.union(&Geometry::empty()) == left);
+        let mut narrow = Geometry::full();
+        narrow.push_face(Orientation::Left, Offset::from(4));
+        narrow.push_face(Orientation::Right, Offset::from(4));
+        assert!(narrow.intersection(&Geometry::full()) == narrow);
+        // Geometry that does not overlap intersects to nothing.
+        let mut far = Geometry::full();
+        far.push_face(Orientation::Left, Offset::from(12));
+        let mut near = Geometry::full();
+        near.push_face(Orientation::Right, Offset::from(12));
+        assert!(near.intersection(&far).is_empty());
+    }
+
+    #[test]
+    fn push_and_pull_faces() {
+        let mut geometry = Geometry::full();
+        geometry.push_face(Orientation::Right, Offset::from(4));
+        geometry.push_face(Orientation::Left, Offset::from(2));
+        for edge in geometry.edges(Axis::X) {
+            assert_eq!(2, edge.front().to_inner());
+            assert_eq!(11, edge.back().to_inner());
+        }
+
+        // Faces cannot be pulled beyond the undeformed cube.
+        geometry.pull_face(Orientation::Right, Offset::max_value());
+        geometry.pull_face(Orientation::Left, Offset::from(2));
+        assert!(geometry.is_full());
+
+        // Faces converge rather than crossing.
+        geometry.push_face(Orientation::Top, Offset::max_value());
+        geometry.push_face(Orientation::Bottom, Offset::max_value());
+        assert!(geometry.is_empty());
+    }
+
+    #[test]
+    fn move_corner_deforms_edges() {
+        let mut geometry = Geometry::full();
+        let offset = Offset::from(8);
+        geometry.move_corner(7, [offset, offset, offset]);
+
+        let unit = UPoint3::new(1, 1, 1);
+        let corner = geometry.map_unit_cube_point(&unit);
+        assert!(corner.coords.iter().all(|x| *x < 1.0));
+        assert!(!geometry.is_full());
+    }
+
+    #[test]
+    fn rotate_and_reflect() {
+        let mut geometry = Geometry::full();
+        geometry.push_face(Orientation::Right, Offset::from(4));
+        geometry.move_corner(0, [Offset::from(2), Offset::from(3), Offset::from(1)]);
+
+        // Four quarter turns and two reflections are identities.
+        assert!(geometry.rotate(Axis::Y, 4) == geometry);
+        assert!(geometry.rotate(Axis::X, -1) == geometry.rotate(Axis::X, 3));
+        assert!(geometry.reflect(Axis::Z).reflect(Axis::Z) == geometry);
+        assert!(geometry.rotate(Axis::Z, 1) != geometry);
+
+        // A quarter turn about the z-axis moves the right face to the top.
+        let mut right = Geometry::full();
+        right.push_face(Orientation::Right, Offset::from(4));
+        let mut top = Geometry::full();
+        top.push_face(Orientation::Top, Offset::from(4));
+        assert!(right.rotate(Axis::Z, 1) == top);
+        // Reflecting across the x-axis moves the right face to the left.
+        let mut left = Geometry::full();
+        left.push_face(Orientation::Left, Offset::from(4));
+        assert!(right.reflect(Axis::X) == left);
+    }
 }
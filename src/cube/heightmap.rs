@@ -0,0 +1,162 @@
+use image::{DynamicImage, GrayImage};
+use std::cmp;
+
+use cube::edit::{Cursor, Edit};
+use cube::geometry::{Edge, Geometry, Offset};
+use cube::space::{Axis, LogWidth};
+use cube::tree::Tree;
+use cube::CubeError;
+use math::{FScalar, UPoint3, UScalar, UVector3};
+use OptionExt;
+
+impl Tree {
+    /// Constructs a new `Tree` from a grayscale heightmap.
+    ///
+    /// Each sample in the image is a column of cubes with the given width
+    /// along the X and Z axes. The height of a sample is its intensity (in the
+    /// range `[0, 255]`) multiplied by `vertical_scale`. Columns are filled up
+    /// to the height of their samples, and the topmost cubes of each column
+    /// deform their `Edge`s along the Y axis to represent fractional heights.
+    /// The heights at the corners of columns are interpolated from adjacent
+    /// samples, so neighboring columns meet smoothly.
+    ///
+    /// The width of the `Tree` is the smallest width that contains the
+    /// heightmap.
+    ///
+    /// Fails if the heightmap does not fit within a `Tree` of the maximum
+    /// width.
+    pub fn from_heightmap(
+        image: &DynamicImage,
+        width: LogWidth,
+        vertical_scale: FScalar,
+    ) -> Result<Self, CubeError> {
+        let image = image.to_luma();
+        let (columns, rows) = image.dimensions();
+        let unit = width.exp();
+        let max = LogWidth::max_value().exp();
+        let height = (255.0 * vertical_scale).ceil();
+        if height > max as FScalar {
+            return Err(CubeError::OutOfBounds);
+        }
+        let extent = cmp::max(columns, rows)
+            .checked_mul(unit)
+            .and_if(|extent| *extent <= max)
+            .ok_or(CubeError::OutOfBounds)?;
+        let extent = cmp::max(extent, height as UScalar);
+        let root = (32 - extent.saturating_sub(1).leading_zeros()) as u8;
+        let mut tree = Tree::empty(LogWidth::new(cmp::max(root, width.to_inner())));
+        for x in 0..columns {
+            for z in 0..rows {
+                let mut heights = [0.0; 4];
+                for (index, height) in heights.iter_mut().enumerate() {
+                    let (u, v) = ((index & 1) as UScalar, (index >> 1) as UScalar);
+                    *height = corner_height(&image, x + u, z + v) * vertical_scale;
+                }
+                fill_column(&mut tree, &UPoint3::new(x * unit, 0, z * unit), width, &heights);
+            }
+        }
+        Ok(tree)
+    }
+}
+
+/// Fills a column of cubes up to the given heights at its corners.
+///
+/// The heights are indexed like the `Edge`s along the Y axis, which are
+/// indexed by their coordinates along the X and Z axes.
+fn fill_column(tree: &mut Tree, origin: &UPoint3, width: LogWidth, heights: &[FScalar; 4]) {
+    let unit = width.exp() as FScalar;
+    let min = heights.iter().cloned().fold(heights[0], FScalar::min);
+    let max = heights.iter().cloned().fold(heights[0], FScalar::max);
+    let full = (min / unit).floor() as UScalar;
+    let partial = (max / unit).ceil() as UScalar;
+    if full > 0 {
+        let cursor = Cursor::at_point_with_span(origin, width, &UVector3::new(0, full - 1, 0));
+        tree.as_cube_mut().apply(&cursor, &Edit::Fill);
+    }
+    for y in full..partial {
+        let bottom = y as FScalar * unit;
+        let mut geometry = Geometry::full();
+        for (edge, height) in geometry.edges_mut(Axis::Y).iter_mut().zip(heights.iter()) {
+            let back = ((height - bottom) / unit) * Offset::max_inner_value() as FScalar;
+            let back = back.round().max(0.0).min(Offset::max_inner_value() as FScalar);
+            *edge = Edge::new(Offset::min_value(), Offset::from(back as u8));
+        }
+        if !geometry.is_empty() {
+            let cursor = Cursor::at_point(&(*origin + UVector3::new(0, y * width.exp(), 0)), width);
+            tree.as_cube_mut().apply(&cursor, &Edit::SetGeometry(geometry));
+        }
+    }
+}
+
+/// Gets the height at a corner of the sample grid by averaging the intensity
+/// of the adjacent samples.
+fn corner_height(image: &GrayImage, x: UScalar, z: UScalar) -> FScalar {
+    let (columns, rows) = image.dimensions();
+    let mut sum = 0.0;
+    let mut count = 0.0;
+    for i in x.saturating_sub(1)..cmp::min(x + 1, columns) {
+        for j in z.saturating_sub(1)..cmp::min(z + 1, rows) {
+            sum += image.get_pixel(i, j).data[0] as FScalar;
+            count += 1.0;
+        }
+    }
+    if count > 0.0 {
+        sum / count
+    }
+    else {
+        0.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{DynamicImage, GrayImage, Luma};
+
+    use cube::space::{Axis, LogWidth, Spatial};
+    use cube::tree::Tree;
+    use math::UPoint3;
+
+    fn heightmap(intensity: u8) -> DynamicImage {
+        DynamicImage::ImageLuma8(GrayImage::from_pixel(2, 2, Luma([intensity])))
+    }
+
+    #[test]
+    fn columns_fill_to_scaled_height() {
+        // Samples with an intensity of 64 are 32 units high, which is two
+        // cubes with a width of 16.
+        let tree = Tree::from_heightmap(&heightmap(64), LogWidth::new(4), 0.5).unwrap();
+        let root = tree.as_cube();
+
+        assert!(root.partition().width() == LogWidth::new(7));
+        for &x in &[0, 16] {
+            for &(y, full) in &[(0, true), (16, true), (32, false)] {
+                let cube = root.at_point(&UPoint3::new(x, y, 16), LogWidth::new(4)).unwrap();
+                let geometry = cube.as_leaf().unwrap().geometry;
+                assert_eq!(full, !geometry.is_empty());
+            }
+        }
+    }
+
+    #[test]
+    fn topmost_cubes_are_deformed() {
+        // Samples with an intensity of 40 are 20 units high, which deforms the
+        // second cube to a quarter of its height.
+        let tree = Tree::from_heightmap(&heightmap(40), LogWidth::new(4), 0.5).unwrap();
+        let root = tree.as_cube();
+
+        let cube = root.at_point(&UPoint3::new(0, 16, 0), LogWidth::new(4)).unwrap();
+        let geometry = cube.as_leaf().unwrap().geometry;
+        for edge in geometry.edges(Axis::Y) {
+            assert_eq!(0, edge.front().to_inner());
+            assert_eq!(4, edge.back().to_inner());
+        }
+    }
+
+    #[test]
+    fn oversized_heightmaps_are_rejected() {
+        // The extent of the columns overflows the width of a `Tree`.
+        assert!(Tree::from_heightmap(&heightmap(64), LogWidth::max_value(), 0.5).is_err());
+        // The height of the samples exceeds the width of a `Tree`.
+        assert!(Tree::from_heightmap(&heightmap(64), LogWidth::new(4), 1.0e12).is_err());
+    }
+}
@@ -0,0 +1,1189 @@
+use plexus::buffer::MeshBuffer;
+use std::cmp;
+use std::collections::{BTreeMap, HashMap};
+
+use cube::geometry::Geometry;
+use cube::space::{self, Direction, Intersects, LogWidth, Orientation, Partition, Spatial, AABB};
+use cube::tree::{BranchPayload, Cube, LeafPayload, Node, OrphanCube};
+use math::{FPoint2, FPoint3, FScalar, FVector3, FromSpace, IntoSpace, UPoint3, UScalar,
+           UVector3};
+use render::{AtlasRect, Color, Index, TextureMapping, TextureProjection, ToMeshBuffer, Vertex};
+use resource::ResourceId;
+use {BoolExt, OptionExt};
+
+mod smooth;
+
+const ORIENTATIONS: [Orientation; 6] = [
+    Orientation::Left,
+    Orientation::Right,
+    Orientation::Top,
+    Orientation::Bottom,
+    Orientation::Front,
+    Orientation::Back,
+];
+
+/// Strategies for generating meshes from cubes.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum Strategy {
+    /// Generates the visible faces of each leaf independently.
+    Faceted,
+    /// Merges visible, coplanar faces of the same material that are not
+    /// deformed into larger quads. Texture coordinates are projected from
+    /// positions, so textures continue to tile across merged quads.
+    Greedy,
+    /// Extracts a smooth surface from the occupancy of the tree, sampled at
+    /// the given width, using surface nets. Leaves are treated as a field
+    /// whose occupancy follows their deformed `Geometry`, and the surface
+    /// crosses the edges between samples on the deformed faces of leaves.
+    /// Narrower widths produce more detailed surfaces at a greater cost.
+    Smooth(LogWidth),
+}
+
+/// Options for generating meshes from cubes.
+#[derive(Clone)]
+pub struct MeshOptions {
+    pub strategy: Strategy,
+    /// Stitches faces to their neighbors so that meshes are watertight.
+    ///
+    /// When enabled, vertices are inserted along the edges of full faces
+    /// where the corners of coplanar neighbors lie on those edges, which
+    /// removes T-junctions between cubes of different widths and deformed
+    /// neighbors whose corners are displaced along the shared edge. Corners
+    /// displaced away from the edge cannot be welded this way. Vertex
+    /// positions are also snapped to a fine grid, so that coincident corners
+    /// computed from different cubes are welded despite rounding. Quads
+    /// merged by the `Greedy` strategy are not stitched.
+    pub stitch: bool,
+    /// Bakes ambient occlusion into the colors of vertices.
+    ///
+    /// The occlusion of a vertex is determined by the occupancy of the four
+    /// narrowest cubes around it just beyond the face of the vertex. Vertices
+    /// are darkened to between 40% and 100% of their brightness.
+    pub ambient_occlusion: bool,
+    /// Texture mappings keyed by material. Materials without a mapping use
+    /// the default `TextureMapping`.
+    pub mappings: HashMap<ResourceId, TextureMapping>,
+    /// Texture atlas rectangles keyed by material. Vertices are tagged with
+    /// the rectangle of their material, within which texture coordinates are
+    /// wrapped. Materials without a rectangle use the entire texture.
+    pub atlas: HashMap<ResourceId, AtlasRect>,
+}
+
+impl Default for MeshOptions {
+    fn default() -> Self {
+        MeshOptions {
+            strategy: Strategy::Faceted,
+            stitch: false,
+            ambient_occlusion: false,
+            mappings: HashMap::new(),
+            atlas: HashMap::new(),
+        }
+    }
+}
+
+impl<'a, 'b> ToMeshBuffer for Cube<'a, &'b Node> {
+    /// Generates a `MeshBuffer` for the leaves in the cube using the default
+    /// `MeshOptions`.
+    fn to_mesh_buffer(&self) -> MeshBuffer<Index, Vertex> {
+        self.to_mesh_buffer_with(&MeshOptions::default())
+    }
+
+    /// Generates a `MeshBuffer` for each material in the cube using the
+    /// default `MeshOptions`.
+    fn to_mesh_buffers(&self) -> BTreeMap<ResourceId, MeshBuffer<Index, Vertex>> {
+        self.to_mesh_buffers_with(&MeshOptions::default())
+    }
+}
+
+impl<'a, 'b> Cube<'a, &'b Node> {
+    /// Generates a `MeshBuffer` for the leaves in the cube.
+    ///
+    /// Faces that are hidden by adjacent cubes within the cube are culled.
+    pub fn to_mesh_buffer_with(&self, options: &MeshOptions) -> MeshBuffer<Index, Vertex> {
+        self.to_mesh_buffer_within(self, options)
+    }
+
+    /// Generates a `MeshBuffer` for the leaves in the cube, culling faces
+    /// that are hidden by adjacent cubes within the given root cube.
+    ///
+    /// This is used to generate meshes for a region of a tree, such as a
+    /// chunk, without exposing faces along the boundary of that region.
+    pub(super) fn to_mesh_buffer_within(
+        &self,
+        root: &Cube<&Node>,
+        options: &MeshOptions,
+    ) -> MeshBuffer<Index, Vertex> {
+        let mut buffer = MeshBuilder::default();
+        for (_, builder) in self.to_mesh_builders(root, options, None) {
+            buffer.append(builder);
+        }
+        buffer.into_mesh_buffer()
+    }
+
+    /// Generates a `MeshBuffer` for each material in the cube.
+    ///
+    /// Buffers are keyed and ordered by material. See
+    /// `ToMeshBuffer::to_mesh_buffers`.
+    pub fn to_mesh_buffers_with(
+        &self,
+        options: &MeshOptions,
+    ) -> BTreeMap<ResourceId, MeshBuffer<Index, Vertex>> {
+        self.to_mesh_builders(self, options, None)
+            .into_iter()
+            .map(|(material, builder)| (material, builder.into_mesh_buffer()))
+            .collect()
+    }
+
+    /// Generates a `MeshBuffer` with a level of detail that decreases with
+    /// distance from the given position, such as the position of a camera.
+    ///
+    /// `thresholds` pairs distances with widths. Branches with a width no
+    /// greater than the largest width whose distance has been reached (as
+    /// measured from their midpoints) are not descended into. Instead, each
+    /// such branch is approximated by a box that bounds its non-empty leaves
+    /// and takes the material that occupies the most volume among them.
+    pub fn to_mesh_buffer_lod(
+        &self,
+        position: &FPoint3,
+        thresholds: &[(FScalar, LogWidth)],
+    ) -> MeshBuffer<Index, Vertex> {
+        self.to_mesh_buffer_lod_with(position, thresholds, &MeshOptions::default())
+    }
+
+    /// Generates a `MeshBuffer` with a level of detail that decreases with
+    /// distance from the given position using the given `MeshOptions`. See
+    /// `to_mesh_buffer_lod`.
+    ///
+    /// Approximated boxes are textured and occluded like leaves of their
+    /// material. The `Smooth` strategy samples the tree at its own width, so
+    /// it ignores the thresholds.
+    pub fn to_mesh_buffer_lod_with(
+        &self,
+        position: &FPoint3,
+        thresholds: &[(FScalar, LogWidth)],
+        options: &MeshOptions,
+    ) -> MeshBuffer<Index, Vertex> {
+        let lod = Lod {
+            position: position,
+            thresholds: thresholds,
+        };
+        let mut buffer = MeshBuilder::default();
+        for (_, builder) in self.to_mesh_builders(self, options, Some(&lod)) {
+            buffer.append(builder);
+        }
+        buffer.into_mesh_buffer()
+    }
+
+    fn to_mesh_builders<'r>(
+        &self,
+        root: &'r Cube<'r, &'r Node>,
+        options: &MeshOptions,
+        lod: Option<&Lod>,
+    ) -> BTreeMap<ResourceId, MeshBuilder<'r>> {
+        let mut builders = BTreeMap::new();
+        if let Strategy::Smooth(width) = options.strategy {
+            smooth::push_surface(root, self, width, options, &mut builders);
+            return builders;
+        }
+        let mut planes = BTreeMap::new();
+        for_each_meshed_cube(self, lod, &mut |cube| {
+            if let Some(leaf) = cube.as_leaf() {
+                if leaf.geometry.is_empty() {
+                    return;
+                }
+                let builder = builders
+                    .entry(leaf.material)
+                    .or_insert_with(|| MeshBuilder::new(root, leaf.material, options));
+                for orientation in &ORIENTATIONS {
+                    if is_face_hidden(root, cube.partition(), &leaf.geometry, *orientation) {
+                        continue;
+                    }
+                    if options.strategy == Strategy::Greedy
+                        && is_face_full(&leaf.geometry, *orientation)
+                    {
+                        planes
+                            .entry(leaf.material)
+                            .or_insert_with(Planes::default)
+                            .push(cube.partition(), *orientation);
+                    }
+                    else if options.stitch && is_face_full(&leaf.geometry, *orientation) {
+                        builder.push_stitched_face(root, cube.partition(), *orientation);
+                    }
+                    else {
+                        builder.push_face(cube.partition(), &leaf.geometry, *orientation);
+                    }
+                }
+            }
+            else if let Some((aabb, material)) = approximate(cube) {
+                builders
+                    .entry(material)
+                    .or_insert_with(|| MeshBuilder::new(root, material, options))
+                    .push_box(&aabb);
+            }
+        });
+        for (material, planes) in planes {
+            if let Some(builder) = builders.get_mut(&material) {
+                planes.merge_into(builder);
+            }
+        }
+        builders
+    }
+}
+
+impl<'a, L, B> ToMeshBuffer for OrphanCube<'a, L, B>
+where
+    L: AsRef<LeafPayload>,
+    B: AsRef<BranchPayload>,
+{
+    /// Generates a `MeshBuffer` for the cube if it is a leaf.
+    ///
+    /// Because an `OrphanCube` has no access to its neighbors, all faces are
+    /// generated.
+    fn to_mesh_buffer(&self) -> MeshBuffer<Index, Vertex> {
+        let mut builder = MeshBuilder::default();
+        if let Some(leaf) = self.as_leaf().and_if(|leaf| !leaf.geometry.is_empty()) {
+            for orientation in &ORIENTATIONS {
+                builder.push_face(self.partition(), &leaf.geometry, *orientation);
+            }
+        }
+        builder.into_mesh_buffer()
+    }
+
+    /// Generates a `MeshBuffer` for the material of the cube if it is a
+    /// non-empty leaf.
+    fn to_mesh_buffers(&self) -> BTreeMap<ResourceId, MeshBuffer<Index, Vertex>> {
+        let mut buffers = BTreeMap::new();
+        if let Some(leaf) = self.as_leaf().and_if(|leaf| !leaf.geometry.is_empty()) {
+            buffers.insert(leaf.material, self.to_mesh_buffer());
+        }
+        buffers
+    }
+}
+
+/// Accumulates faces into vertex and index buffers.
+///
+/// Identical vertices are shared, so each distinct `Vertex` appears only once
+/// in the vertex buffer.
+#[derive(Default)]
+struct MeshBuilder<'r> {
+    indices: Vec<Index>,
+    vertices: Vec<Vertex>,
+    lookup: HashMap<Vertex, Index>,
+    snap: bool,
+    /// The root cube used to compute ambient occlusion, if enabled.
+    occluder: Option<&'r Cube<'r, &'r Node>>,
+    mapping: TextureMapping,
+    rect: AtlasRect,
+}
+
+impl<'r> MeshBuilder<'r> {
+    fn new(root: &'r Cube<'r, &'r Node>, material: ResourceId, options: &MeshOptions) -> Self {
+        MeshBuilder {
+            snap: options.stitch,
+            occluder: options.ambient_occlusion.into_some(root),
+            mapping: options
+                .mappings
+                .get(&material)
+                .cloned()
+                .unwrap_or_default(),
+            rect: options.atlas.get(&material).cloned().unwrap_or_default(),
+            ..MeshBuilder::default()
+        }
+    }
+
+    /// Pushes a face of a leaf.
+    fn push_face(&mut self, partition: &Partition, geometry: &Geometry, orientation: Orientation) {
+        let origin: FVector3 = partition.origin().coords.into_space();
+        let width = partition.width().exp() as FScalar;
+        let mut positions = [FPoint3::origin(); 4];
+        for (position, unit) in positions.iter_mut().zip(face(orientation).iter()) {
+            *position = (geometry.map_unit_cube_point(unit) * width) + origin;
+        }
+        self.push_quad(&positions, orientation);
+    }
+
+    /// Pushes the faces of an axis-aligned box.
+    ///
+    /// The extent of an `AABB` is inclusive, so the faces of the box lie one
+    /// unit beyond it.
+    fn push_box(&mut self, aabb: &AABB) {
+        let origin: FPoint3 = aabb.origin.into_space();
+        let extent: FVector3 = (aabb.extent + UVector3::new(1, 1, 1)).into_space();
+        for orientation in &ORIENTATIONS {
+            let mut positions = [FPoint3::origin(); 4];
+            for (position, unit) in positions.iter_mut().zip(face(*orientation).iter()) {
+                *position = origin
+                    + FVector3::new(
+                        unit.x as FScalar * extent.x,
+                        unit.y as FScalar * extent.y,
+                        unit.z as FScalar * extent.z,
+                    );
+            }
+            self.push_quad(&positions, *orientation);
+        }
+    }
+
+    /// Pushes a full face of a leaf, inserting vertices along its edges
+    /// where the corners of coplanar neighbors in the given root cube lie on
+    /// them. Faces with inserted vertices are triangulated as a fan about
+    /// their midpoint.
+    fn push_stitched_face(
+        &mut self,
+        root: &Cube<&Node>,
+        partition: &Partition,
+        orientation: Orientation,
+    ) {
+        let (axis, _, _) = plane_axes(orientation);
+        let origin = *partition.origin();
+        let width = partition.width().exp();
+        let corners = face(orientation);
+        let mut polygon = vec![];
+        for index in 0..4 {
+            let (a, b) = (&corners[index], &corners[(index + 1) % 4]);
+            let start = FPoint3::from_space(origin + (a.coords * width));
+            polygon.push(start);
+            // The edge runs along `along` and lies on the side of the face
+            // given by `across`. Vertices are inserted where the corners of
+            // the faces of the leaves of the neighbor on that side lie on the
+            // edge, including corners of deformed leaves that are displaced
+            // along the edge.
+            let (along, across) = if a[(axis + 1) % 3] != b[(axis + 1) % 3] {
+                ((axis + 1) % 3, (axis + 2) % 3)
+            }
+            else {
+                ((axis + 2) % 3, (axis + 1) % 3)
+            };
+            let mut point = origin;
+            if a[across] == 0 {
+                if point[across] < width {
+                    continue;
+                }
+                point[across] -= width;
+            }
+            else {
+                point[across] += width;
+            }
+            let line = snap(&start);
+            let (lower, upper) = (
+                origin[along] as FScalar,
+                (origin[along] + width) as FScalar,
+            );
+            let mut splits = vec![];
+            if let Some(neighbor) = root.at_point(&point, partition.width()) {
+                let constraints = [(across, 1 - a[across]), (axis, side(orientation))];
+                for_each_leaf_at(&neighbor, &constraints, &mut |cube| {
+                    if let Some(leaf) = cube.as_leaf().and_if(|leaf| !leaf.geometry.is_empty()) {
+                        let origin: FVector3 = cube.partition().origin().coords.into_space();
+                        let width = cube.partition().width().exp() as FScalar;
+                        for unit in &face(orientation) {
+                            let corner =
+                                snap(&((leaf.geometry.map_unit_cube_point(unit) * width) + origin));
+                            if corner[axis] == line[axis]
+                                && corner[across] == line[across]
+                                && corner[along] > lower
+                                && corner[along] < upper
+                            {
+                                splits.push(corner[along]);
+                            }
+                        }
+                    }
+                });
+            }
+            splits.sort_by(|x, y| x.partial_cmp(y).unwrap());
+            splits.dedup();
+            if a[along] > b[along] {
+                splits.reverse();
+            }
+            for split in splits {
+                let mut point = start;
+                point[along] = split;
+                polygon.push(point);
+            }
+        }
+        if polygon.len() == 4 {
+            self.push_quad(&[polygon[0], polygon[1], polygon[2], polygon[3]], orientation);
+        }
+        else {
+            let midpoint = origin + ((corners[0].coords + corners[2].coords) * (width / 2));
+            // The midpoint is pushed after the polygon, so it has the index
+            // `n` and each triangle fans out from it.
+            let n = polygon.len();
+            let triangles = (0..n)
+                .map(|index| [n, index, (index + 1) % n])
+                .collect::<Vec<_>>();
+            polygon.push(FPoint3::from_space(midpoint));
+            self.push_polygon(&polygon, &triangles, orientation);
+        }
+    }
+
+    /// Pushes a quad as a pair of triangles.
+    fn push_quad(&mut self, positions: &[FPoint3; 4], orientation: Orientation) {
+        self.push_polygon(positions, &[[0, 1, 2], [0, 2, 3]], orientation);
+    }
+
+    /// Pushes triangles given by indices into the positions of a polygon.
+    /// Texture coordinates are projected over the entire polygon. Triangles
+    /// that have collapsed due to deformation are discarded.
+    fn push_polygon(
+        &mut self,
+        positions: &[FPoint3],
+        triangles: &[[usize; 3]],
+        orientation: Orientation,
+    ) {
+        let positions = positions
+            .iter()
+            .map(|position| if self.snap { snap(position) } else { *position })
+            .collect::<Vec<_>>();
+        let uvs = self.project(&positions, orientation);
+        for triangle in triangles {
+            let (a, b, c) = (
+                &positions[triangle[0]],
+                &positions[triangle[1]],
+                &positions[triangle[2]],
+            );
+            if a == b || b == c || c == a {
+                continue;
+            }
+            for index in triangle {
+                let position = &positions[*index];
+                let color = match self.occluder {
+                    Some(root) => {
+                        let brightness = occlusion(root, position, orientation);
+                        Color::new(brightness, brightness, brightness, 1.0)
+                    }
+                    _ => Color::white(),
+                };
+                self.push_vertex(
+                    Vertex::new(position, &uvs[*index], &color).with_rect(&self.rect),
+                );
+            }
+        }
+    }
+
+    /// Projects the positions of a polygon to texture coordinates using the
+    /// `TextureMapping` of the builder.
+    fn project(&self, positions: &[FPoint3], orientation: Orientation) -> Vec<FPoint2> {
+        let orientation = match self.mapping.projection {
+            TextureProjection::Triplanar => dominant_orientation(positions).unwrap_or(orientation),
+            _ => orientation,
+        };
+        let mut uvs = positions
+            .iter()
+            .map(|position| uv(orientation, position))
+            .collect::<Vec<_>>();
+        if self.mapping.projection == TextureProjection::Stretch {
+            let (mut lower, mut upper) = (uvs[0], uvs[0]);
+            for uv in &uvs {
+                for axis in 0..2 {
+                    lower[axis] = lower[axis].min(uv[axis]);
+                    upper[axis] = upper[axis].max(uv[axis]);
+                }
+            }
+            for uv in &mut uvs {
+                for axis in 0..2 {
+                    let extent = upper[axis] - lower[axis];
+                    uv[axis] = if extent > 0.0 {
+                        (uv[axis] - lower[axis]) / extent
+                    }
+                    else {
+                        0.0
+                    };
+                }
+            }
+        }
+        uvs.iter().map(|uv| self.mapping.transform(uv)).collect()
+    }
+
+    /// Pushes the triangles of another `MeshBuilder`.
+    fn append(&mut self, other: MeshBuilder<'r>) {
+        for index in other.indices {
+            self.push_vertex(other.vertices[index as usize]);
+        }
+    }
+
+    /// Pushes the index of a vertex, pushing the vertex itself only if an
+    /// identical vertex has not already been pushed.
+    fn push_vertex(&mut self, vertex: Vertex) {
+        let vertices = &mut self.vertices;
+        let index = *self.lookup.entry(vertex).or_insert_with(|| {
+            vertices.push(vertex);
+            (vertices.len() - 1) as Index
+        });
+        self.indices.push(index);
+    }
+
+    fn into_mesh_buffer(self) -> MeshBuffer<Index, Vertex> {
+        MeshBuffer::from_raw_buffers(self.indices, self.vertices).unwrap()
+    }
+}
+
+/// Full faces grouped by their `Orientation` and the plane in which they lie.
+///
+/// Faces are stored as rectangles `[u0, u1, v0, v1]` in the plane, where `u`
+/// and `v` are the axes orthogonal to the axis of the `Orientation`.
+#[derive(Default)]
+struct Planes(BTreeMap<(usize, UScalar), Vec<[UScalar; 4]>>);
+
+impl Planes {
+    fn push(&mut self, partition: &Partition, orientation: Orientation) {
+        let (axis, u, v) = plane_axes(orientation);
+        let origin = partition.origin();
+        let width = partition.width().exp();
+        let index = ORIENTATIONS.iter().position(|x| *x == orientation).unwrap();
+        self.0
+            .entry((index, origin[axis] + (side(orientation) * width)))
+            .or_insert_with(Vec::new)
+            .push([origin[u], origin[u] + width, origin[v], origin[v] + width]);
+    }
+
+    /// Greedily merges the faces in each plane into larger quads and pushes
+    /// them into a `MeshBuilder`.
+    ///
+    /// Faces are rasterized into a grid formed by their boundaries. Quads are
+    /// grown from each unvisited cell first along `u` and then along `v`.
+    fn merge_into(self, builder: &mut MeshBuilder) {
+        for ((index, plane), rectangles) in self.0 {
+            let orientation = ORIENTATIONS[index];
+            let (axis, u, v) = plane_axes(orientation);
+            let us = boundaries(rectangles.iter().flat_map(|rectangle| {
+                vec![rectangle[0], rectangle[1]]
+            }));
+            let vs = boundaries(rectangles.iter().flat_map(|rectangle| {
+                vec![rectangle[2], rectangle[3]]
+            }));
+            let (columns, rows) = (us.len() - 1, vs.len() - 1);
+            let search = |values: &[UScalar], value| values.binary_search(&value).unwrap();
+            let mut cells = vec![false; columns * rows];
+            for rectangle in &rectangles {
+                for j in search(&vs, rectangle[2])..search(&vs, rectangle[3]) {
+                    for i in search(&us, rectangle[0])..search(&us, rectangle[1]) {
+                        cells[i + (j * columns)] = true;
+                    }
+                }
+            }
+            for j in 0..rows {
+                for i in 0..columns {
+                    if !cells[i + (j * columns)] {
+                        continue;
+                    }
+                    let mut i1 = i + 1;
+                    while i1 < columns && cells[i1 + (j * columns)] {
+                        i1 += 1;
+                    }
+                    let mut j1 = j + 1;
+                    while j1 < rows && (i..i1).all(|k| cells[k + (j1 * columns)]) {
+                        j1 += 1;
+                    }
+                    for y in j..j1 {
+                        for x in i..i1 {
+                            cells[x + (y * columns)] = false;
+                        }
+                    }
+                    let mut positions = [FPoint3::origin(); 4];
+                    for (position, unit) in positions.iter_mut().zip(face(orientation).iter()) {
+                        let mut point = UPoint3::origin();
+                        point[axis] = plane;
+                        point[u] = if unit[u] == 0 { us[i] } else { us[i1] };
+                        point[v] = if unit[v] == 0 { vs[j] } else { vs[j1] };
+                        *position = FPoint3::from_space(point);
+                    }
+                    builder.push_quad(&positions, orientation);
+                }
+            }
+        }
+    }
+}
+
+/// Level-of-detail parameters. See `Cube::to_mesh_buffer_lod`.
+struct Lod<'a> {
+    position: &'a FPoint3,
+    thresholds: &'a [(FScalar, LogWidth)],
+}
+
+impl<'a> Lod<'a> {
+    /// Gets the width at which cubes in the given `Partition` are
+    /// approximated rather than descended into.
+    fn width(&self, partition: &Partition) -> LogWidth {
+        let midpoint: FPoint3 = partition.midpoint().into_space();
+        let distance = (midpoint - *self.position).norm();
+        self.thresholds
+            .iter()
+            .filter(|&&(threshold, _)| distance >= threshold)
+            .map(|&(_, width)| width)
+            .max()
+            .unwrap_or_else(LogWidth::min_value)
+    }
+}
+
+/// Visits the cubes in a cube that are meshed: leaves and, if a `Lod` is
+/// given, branches that are approximated.
+fn for_each_meshed_cube<F>(cube: &Cube<&Node>, lod: Option<&Lod>, f: &mut F)
+where
+    F: FnMut(&Cube<&Node>),
+{
+    let width = cube.partition().width();
+    if cube.is_leaf() || lod.map_or(false, |lod| width <= lod.width(cube.partition())) {
+        f(cube);
+    }
+    else {
+        for index in 0..8 {
+            if let Some(cube) = cube.at_index(index) {
+                for_each_meshed_cube(&cube, lod, f);
+            }
+        }
+    }
+}
+
+/// Approximates a cube by the box bounding its non-empty leaves and the
+/// material that occupies the most volume among those leaves.
+///
+/// Returns `None` if the cube is empty.
+fn approximate(cube: &Cube<&Node>) -> Option<(AABB, ResourceId)> {
+    let mut bounds: Option<AABB> = None;
+    let mut volumes = BTreeMap::new();
+    for cube in cube.iter() {
+        if let Some(leaf) = cube.as_leaf().and_if(|leaf| !leaf.geometry.is_empty()) {
+            let aabb = cube.partition().aabb();
+            bounds = Some(match bounds {
+                Some(bounds) => bounds.union(&aabb),
+                None => aabb,
+            });
+            let width = cube.partition().width().exp() as FScalar;
+            *volumes.entry(leaf.material).or_insert(0.0) += width * width * width;
+        }
+    }
+    bounds.map(|bounds| {
+        let (material, _) = volumes.into_iter().fold(
+            (0, 0.0),
+            |(material, volume), (other, other_volume)| {
+                if other_volume > volume {
+                    (other, other_volume)
+                }
+                else {
+                    (material, volume)
+                }
+            },
+        );
+        (bounds, material)
+    })
+}
+
+/// Gets the axis of an `Orientation` and the axes of its plane.
+fn plane_axes(orientation: Orientation) -> (usize, usize, usize) {
+    let axis = orientation.axis() as usize;
+    (axis, (axis + 1) % 3, (axis + 2) % 3)
+}
+
+/// Sorts and deduplicates boundaries along an axis.
+fn boundaries<I>(values: I) -> Vec<UScalar>
+where
+    I: IntoIterator<Item = UScalar>,
+{
+    let mut values = values.into_iter().collect::<Vec<_>>();
+    values.sort();
+    values.dedup();
+    values
+}
+
+/// Determines if a face of a leaf is hidden by the cubes adjacent to it in
+/// the given root cube.
+///
+/// A face is hidden if it lies entirely in the plane of the corresponding
+/// face of the unit cube and the adjacent cubes are full along that plane,
+/// regardless of their width. Faces on the boundary of the root cube are
+/// never hidden.
+fn is_face_hidden(
+    root: &Cube<&Node>,
+    partition: &Partition,
+    geometry: &Geometry,
+    orientation: Orientation,
+) -> bool {
+    let axis = orientation.axis() as usize;
+    let side = side(orientation);
+    let is_coplanar = face(orientation).iter().all(|unit| {
+        geometry.map_unit_cube_point(unit)[axis] == side as FScalar
+    });
+    if !is_coplanar {
+        return false;
+    }
+    let width = partition.width().exp();
+    let mut point = *partition.origin();
+    if side == 0 {
+        if point[axis] < width {
+            return false;
+        }
+        point[axis] -= width;
+    }
+    else {
+        point[axis] += width;
+    }
+    root.at_point(&point, partition.width())
+        .map_or(false, |neighbor| covers(&neighbor, orientation.opposite()))
+}
+
+/// Visits the leaves in a cube whose units along the given axes have the
+/// given values, such as the leaves adjacent to a face or an edge.
+fn for_each_leaf_at<F>(cube: &Cube<&Node>, constraints: &[(usize, UScalar)], f: &mut F)
+where
+    F: FnMut(&Cube<&Node>),
+{
+    if cube.is_leaf() {
+        f(cube);
+    }
+    else {
+        for index in 0..8 {
+            let unit = space::unit_at_index(index);
+            if constraints.iter().all(|&(axis, value)| unit[axis] == value) {
+                if let Some(cube) = cube.at_index(index) {
+                    for_each_leaf_at(&cube, constraints, f);
+                }
+            }
+        }
+    }
+}
+
+/// Gets the material of the leaf at a point in a root cube.
+///
+/// Returns `None` if the point lies outside of the deformed `Geometry` of the
+/// leaf or outside of the root cube.
+fn occupancy(root: &Cube<&Node>, point: &UPoint3) -> Option<ResourceId> {
+    occupant(root, point).map(|(_, _, material)| material)
+}
+
+/// Gets the `Partition`, `Geometry`, and material of the leaf at a point in a
+/// root cube.
+///
+/// Returns `None` if the point lies outside of the deformed `Geometry` of the
+/// leaf or outside of the root cube.
+fn occupant(root: &Cube<&Node>, point: &UPoint3) -> Option<(Partition, Geometry, ResourceId)> {
+    if !root.partition().aabb().intersects(point) {
+        return None;
+    }
+    root.at_point(point, LogWidth::min_value()).and_then(|cube| {
+        cube.as_leaf().and_then(|leaf| {
+            let origin: FPoint3 = (*cube.partition().origin()).into_space();
+            let width = cube.partition().width().exp() as FScalar;
+            let point: FPoint3 = (*point).into_space();
+            let unit = FPoint3::origin() + ((point - origin) / width);
+            if leaf.geometry.contains_unit_point(&unit) {
+                Some((*cube.partition(), leaf.geometry, leaf.material))
+            }
+            else {
+                None
+            }
+        })
+    })
+}
+
+/// Gets the `Orientation` along the axis that most closely matches the normal
+/// of a counter-clockwise polygon.
+///
+/// Returns `None` if the polygon is degenerate.
+fn dominant_orientation(positions: &[FPoint3]) -> Option<Orientation> {
+    // Compute the normal using Newell's method, which tolerates non-planar
+    // polygons such as deformed quads.
+    let mut normal = FVector3::new(0.0, 0.0, 0.0);
+    for (index, a) in positions.iter().enumerate() {
+        let b = &positions[(index + 1) % positions.len()];
+        normal.x += (a.y - b.y) * (a.z + b.z);
+        normal.y += (a.z - b.z) * (a.x + b.x);
+        normal.z += (a.x - b.x) * (a.y + b.y);
+    }
+    let axis = (0..3)
+        .max_by(|a, b| {
+            normal[*a]
+                .abs()
+                .partial_cmp(&normal[*b].abs())
+                .unwrap_or(cmp::Ordering::Equal)
+        })
+        .unwrap_or(0);
+    (normal[axis] != 0.0).into_some(orientation(axis, normal[axis] > 0.0))
+}
+
+/// Gets the `Orientation` of a face from its axis and whether it faces in the
+/// positive direction along that axis.
+fn orientation(axis: usize, positive: bool) -> Orientation {
+    match (axis, positive) {
+        (0, true) => Orientation::Right,
+        (0, false) => Orientation::Left,
+        (1, true) => Orientation::Top,
+        (1, false) => Orientation::Bottom,
+        (2, true) => Orientation::Front,
+        _ => Orientation::Back,
+    }
+}
+
+/// Gets the ambient occlusion of a vertex as a brightness in `[0.4, 1.0]`.
+///
+/// The narrowest cubes around the vertex just beyond its face are probed.
+/// Each occupied cube darkens the vertex, up to three cubes.
+fn occlusion(root: &Cube<&Node>, position: &FPoint3, orientation: Orientation) -> FScalar {
+    let (axis, u, v) = plane_axes(orientation);
+    let offset = (LogWidth::min_value().exp() / 2) as FScalar;
+    let normal = if side(orientation) == 1 { offset } else { -offset };
+    let mut count = 0;
+    for &(du, dv) in &[(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)] {
+        let mut probe = *position;
+        probe[axis] += normal;
+        probe[u] += du * offset;
+        probe[v] += dv * offset;
+        if probe.coords.iter().any(|x| *x < 0.0) {
+            continue;
+        }
+        let point = UPoint3::new(probe.x as UScalar, probe.y as UScalar, probe.z as UScalar);
+        if occupancy(root, &point).is_some() {
+            count += 1;
+        }
+    }
+    1.0 - (0.2 * cmp::min(count, 3) as FScalar)
+}
+
+/// Snaps a point to a grid with a resolution finer than the deformation of
+/// the narrowest cubes.
+fn snap(point: &FPoint3) -> FPoint3 {
+    const RESOLUTION: FScalar = 256.0;
+    FPoint3::new(
+        (point.x * RESOLUTION).round() / RESOLUTION,
+        (point.y * RESOLUTION).round() / RESOLUTION,
+        (point.z * RESOLUTION).round() / RESOLUTION,
+    )
+}
+
+/// Determines if a cube is full along the face with the given `Orientation`.
+///
+/// Branches cover a face if all of their subdivisions adjacent to that face
+/// cover it.
+fn covers(cube: &Cube<&Node>, orientation: Orientation) -> bool {
+    if let Some(leaf) = cube.as_leaf() {
+        is_face_full(&leaf.geometry, orientation)
+    }
+    else {
+        let axis = orientation.axis() as usize;
+        (0..8)
+            .filter(|index| space::unit_at_index(*index)[axis] == side(orientation))
+            .all(|index| cube.at_index(index).map_or(false, |cube| covers(&cube, orientation)))
+    }
+}
+
+/// Determines if a face of a `Geometry` is the full, undeformed face of the
+/// unit cube.
+fn is_face_full(geometry: &Geometry, orientation: Orientation) -> bool {
+    face(orientation)
+        .iter()
+        .all(|unit| geometry.map_unit_cube_point(unit) == FPoint3::from_space(*unit))
+}
+
+/// Gets the coordinate of a face of the unit cube along its `Axis`.
+fn side(orientation: Orientation) -> UScalar {
+    match orientation.direction() {
+        Direction::Positive => 0,
+        Direction::Negative => 1,
+    }
+}
+
+/// Gets the corners of a face of the unit cube in counter-clockwise order
+/// when viewed from outside of the cube.
+fn face(orientation: Orientation) -> [UPoint3; 4] {
+    let corners = match orientation {
+        Orientation::Left => [[0, 0, 0], [0, 0, 1], [0, 1, 1], [0, 1, 0]],
+        Orientation::Right => [[1, 0, 1], [1, 0, 0], [1, 1, 0], [1, 1, 1]],
+        Orientation::Top => [[0, 1, 1], [1, 1, 1], [1, 1, 0], [0, 1, 0]],
+        Orientation::Bottom => [[0, 0, 0], [1, 0, 0], [1, 0, 1], [0, 0, 1]],
+        Orientation::Front => [[0, 0, 1], [1, 0, 1], [1, 1, 1], [0, 1, 1]],
+        Orientation::Back => [[1, 0, 0], [0, 0, 0], [0, 1, 0], [1, 1, 0]],
+    };
+    let mut face = [UPoint3::origin(); 4];
+    for (point, corner) in face.iter_mut().zip(corners.iter()) {
+        *point = UPoint3::new(corner[0], corner[1], corner[2]);
+    }
+    face
+}
+
+/// Projects a point onto the plane of a face to get its texture coordinates.
+fn uv(orientation: Orientation, point: &FPoint3) -> FPoint2 {
+    fn map(x: FScalar) -> FScalar {
+        x / LogWidth::unit().exp() as FScalar
+    }
+    match orientation {
+        Orientation::Front => FPoint2::new(map(point.x), map(point.y)),
+        Orientation::Back => FPoint2::new(-map(point.x), map(point.y)),
+        Orientation::Left => FPoint2::new(map(point.z), map(point.y)),
+        Orientation::Right => FPoint2::new(-map(point.z), map(point.y)),
+        Orientation::Top => FPoint2::new(map(point.x), -map(point.z)),
+        Orientation::Bottom => FPoint2::new(map(point.x), map(point.z)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use plexus::buffer::MeshBuffer;
+
+    use cube::{Geometry, LogWidth, MeshOptions, Offset, Orientation, Strategy, Tree};
+    use math::{FPoint2, FPoint3, FVector2, UPoint3};
+    use render::{AtlasRect, Index, TextureMapping, TextureProjection, ToMeshBuffer, Vertex};
+
+    /// Determines if any triangle in the bottom plane of a mesh whose
+    /// vertices satisfy a predicate has a vertex at the given point.
+    fn has_bottom_vertex<F>(buffer: &MeshBuffer<Index, Vertex>, point: &FPoint3, f: F) -> bool
+    where
+        F: Fn(&FPoint3) -> bool,
+    {
+        let positions = buffer
+            .as_vertex_slice()
+            .iter()
+            .map(|vertex| {
+                FPoint3::new(vertex.position[0], vertex.position[1], vertex.position[2])
+            })
+            .collect::<Vec<_>>();
+        buffer.as_index_slice().chunks(3).any(|triangle| {
+            let triangle = triangle
+                .iter()
+                .map(|index| positions[*index as usize])
+                .collect::<Vec<_>>();
+            triangle
+                .iter()
+                .all(|position| position.y == 0.0 && f(position))
+                && triangle
+                    .iter()
+                    .any(|position| (*position - *point).norm() < 1.0e-3)
+        })
+    }
+
+    #[test]
+    fn culls_hidden_faces() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut().subdivide();
+        let buffer = tree.as_cube().to_mesh_buffer();
+
+        // Each of the eight subdivisions exposes three faces of two triangles.
+        assert_eq!(8 * 3 * 2 * 3, buffer.as_index_slice().len());
+    }
+
+    #[test]
+    fn greedy_merges_coplanar_faces() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut().subdivide();
+        let buffer = tree.as_cube().to_mesh_buffer_with(&MeshOptions {
+            strategy: Strategy::Greedy,
+            ..MeshOptions::default()
+        });
+
+        // The four faces along each side of the cube merge into one quad.
+        assert_eq!(6 * 2 * 3, buffer.as_index_slice().len());
+    }
+
+    #[test]
+    fn deduplicates_vertices() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut().subdivide();
+        let buffer = tree.as_cube().to_mesh_buffer();
+
+        // Each side of the cube shares a three-by-three grid of vertices.
+        let vertices = buffer.as_vertex_slice();
+        assert!(vertices.len() <= 6 * 9);
+        for (index, vertex) in vertices.iter().enumerate() {
+            assert!(vertices[(index + 1)..].iter().all(|other| other != vertex));
+        }
+    }
+
+    #[test]
+    fn lod_approximates_distant_branches() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut().subdivide();
+        let position = FPoint3::new(-4096.0, 0.0, 0.0);
+        let buffer = tree.as_cube()
+            .to_mesh_buffer_lod(&position, &[(1024.0, LogWidth::new(8))]);
+
+        // The root is approximated by a single box that spans its width.
+        assert_eq!(6 * 2 * 3, buffer.as_index_slice().len());
+        for vertex in buffer.as_vertex_slice() {
+            assert!(vertex.position.iter().all(|x| *x == 0.0 || *x == 256.0));
+        }
+    }
+
+    #[test]
+    fn lod_applies_options_to_majority_material() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        {
+            let mut cube = tree.as_cube_mut();
+            let _ = cube.subdivide();
+            for index in 0..6 {
+                if let Some(mut cube) = cube.at_index_mut(index) {
+                    if let Some(leaf) = cube.as_leaf_mut() {
+                        leaf.material = 1;
+                    }
+                }
+            }
+        }
+        let mut options = MeshOptions::default();
+        options.atlas.insert(
+            1,
+            AtlasRect::new(FPoint2::new(0.5, 0.0), FVector2::new(0.5, 0.5)),
+        );
+        let position = FPoint3::new(-4096.0, 0.0, 0.0);
+        let buffer = tree.as_cube()
+            .to_mesh_buffer_lod_with(&position, &[(1024.0, LogWidth::new(8))], &options);
+
+        // The box takes the material of most of the leaves and its rectangle.
+        assert_eq!(6 * 2 * 3, buffer.as_index_slice().len());
+        for vertex in buffer.as_vertex_slice() {
+            assert_eq!([0.5, 0.0, 0.5, 0.5], vertex.rect);
+        }
+    }
+
+    #[test]
+    fn stitch_removes_t_junctions() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut().subdivide();
+        let _ = tree.as_cube_mut().at_index_mut(0).unwrap().subdivide();
+        let options = MeshOptions {
+            stitch: true,
+            ..MeshOptions::default()
+        };
+        let faceted = tree.as_cube().to_mesh_buffer();
+        let stitched = tree.as_cube().to_mesh_buffer_with(&options);
+
+        // Faces adjacent to the subdivided cube are split into fans that
+        // share the corners of its subdivisions.
+        assert!(stitched.as_index_slice().len() > faceted.as_index_slice().len());
+        let corner = FPoint3::new(128.0, 0.0, 64.0);
+        assert!(has_bottom_vertex(&stitched, &corner, |position| position.x >= 128.0));
+        assert!(!has_bottom_vertex(&faceted, &corner, |position| position.x >= 128.0));
+    }
+
+    #[test]
+    fn stitch_welds_deformed_neighbors() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        {
+            let mut cube = tree.as_cube_mut();
+            let _ = cube.subdivide();
+            // Displace the corner of the neighbor along the edge that its
+            // bottom face shares with the bottom face of the cube at the
+            // origin.
+            let mut neighbor = cube.at_point_mut(&UPoint3::new(128, 0, 0), LogWidth::new(7))
+                .unwrap();
+            let leaf = neighbor.as_leaf_mut().unwrap();
+            leaf.geometry.move_corner(0, [Offset::from(0), Offset::from(0), Offset::from(4)]);
+        }
+        let options = MeshOptions {
+            stitch: true,
+            ..MeshOptions::default()
+        };
+        let faceted = tree.as_cube().to_mesh_buffer();
+        let stitched = tree.as_cube().to_mesh_buffer_with(&options);
+
+        // The full face of the cube at the origin shares the displaced corner.
+        let corner = FPoint3::new(128.0, 0.0, 128.0 * (4.0 / 15.0));
+        assert!(has_bottom_vertex(&faceted, &corner, |position| position.x >= 128.0));
+        assert!(has_bottom_vertex(&stitched, &corner, |position| position.x <= 128.0));
+        assert!(!has_bottom_vertex(&faceted, &corner, |position| position.x <= 128.0));
+    }
+
+    #[test]
+    fn smooth_surface_is_closed() {
+        let tree = Tree::new(LogWidth::new(6));
+        let buffer = tree.as_cube().to_mesh_buffer_with(&MeshOptions {
+            strategy: Strategy::Smooth(LogWidth::new(4)),
+            ..MeshOptions::default()
+        });
+
+        // A full cube sampled at four samples along each axis produces a
+        // closed surface with four by four quads on each side.
+        assert_eq!(6 * 16 * 2 * 3, buffer.as_index_slice().len());
+    }
+
+    #[test]
+    fn smooth_surface_follows_deformation() {
+        let mut tree = Tree::new(LogWidth::new(6));
+        let options = MeshOptions {
+            strategy: Strategy::Smooth(LogWidth::new(4)),
+            ..MeshOptions::default()
+        };
+        let top = |buffer: &MeshBuffer<Index, Vertex>| {
+            buffer
+                .as_vertex_slice()
+                .iter()
+                .map(|vertex| vertex.position[1])
+                .fold(0.0, |max: f32, y| max.max(y))
+        };
+        let full = top(&tree.as_cube().to_mesh_buffer_with(&options));
+        tree.as_cube_mut()
+            .as_leaf_mut()
+            .unwrap()
+            .geometry
+            .push_face(Orientation::Top, Offset::from(7));
+        let pushed = top(&tree.as_cube().to_mesh_buffer_with(&options));
+
+        // The surface lies on the pushed face rather than midway between the
+        // samples on either side of it.
+        assert!((full - 64.0).abs() < 1.0e-3);
+        assert!((pushed - (64.0 * (8.0 / 15.0))).abs() < 1.0e-3);
+    }
+
+    #[test]
+    fn ambient_occlusion_darkens_corners() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        {
+            let mut cube = tree.as_cube_mut();
+            let _ = cube.subdivide();
+            if let Some(mut cube) = cube.at_index_mut(0) {
+                if let Some(leaf) = cube.as_leaf_mut() {
+                    leaf.geometry = Geometry::empty();
+                }
+            }
+        }
+        let buffer = tree.as_cube().to_mesh_buffer_with(&MeshOptions {
+            ambient_occlusion: true,
+            ..MeshOptions::default()
+        });
+
+        // Faces within the concave corner left by the empty cube are darkened.
+        let vertices = buffer.as_vertex_slice();
+        assert!(vertices.iter().any(|vertex| vertex.color[0] < 1.0));
+        assert!(vertices.iter().all(|vertex| vertex.color[0] >= 0.4 - 1.0e-6));
+    }
+
+    #[test]
+    fn stretch_maps_faces_to_unit_square() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut().subdivide();
+        let mut options = MeshOptions::default();
+        options
+            .mappings
+            .insert(0, TextureMapping::new(TextureProjection::Stretch));
+        let buffer = tree.as_cube().to_mesh_buffer_with(&options);
+
+        // Every face spans the entire texture regardless of its width.
+        let near = |x: f32, y: f32| (x - y).abs() < 1.0e-5;
+        let vertices = buffer.as_vertex_slice();
+        assert!(
+            vertices
+                .iter()
+                .any(|vertex| vertex.uv.iter().all(|x| near(*x, 1.0)))
+        );
+        for vertex in vertices {
+            assert!(vertex.uv.iter().all(|x| near(*x, 0.0) || near(*x, 1.0)));
+        }
+    }
+
+    #[test]
+    fn batches_by_material() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        {
+            let mut cube = tree.as_cube_mut();
+            let _ = cube.subdivide();
+            if let Some(mut cube) = cube.at_index_mut(0) {
+                if let Some(leaf) = cube.as_leaf_mut() {
+                    leaf.material = 1;
+                }
+            }
+        }
+        let buffers = tree.as_cube().to_mesh_buffers();
+
+        // The relabeled cube exposes three faces and the remaining seven
+        // cubes expose three faces each.
+        assert_eq!(2, buffers.len());
+        assert_eq!(3 * 2 * 3, buffers[&1].as_index_slice().len());
+        assert_eq!(7 * 3 * 2 * 3, buffers[&0].as_index_slice().len());
+    }
+}
@@ -0,0 +1,223 @@
+//! Smooth surface extraction using surface nets.
+//!
+//! The tree is sampled on a regular grid, where each sample is either empty
+//! or occupied by a leaf. A vertex is placed in each cell of the dual grid
+//! (formed by the samples) that the surface crosses, at the average of the
+//! points where the crossing edges of that cell leave the deformed
+//! `Geometry` of their occupied leaf. Each edge between samples that crosses
+//! the surface is connected by a quad through the vertices of the four cells
+//! around it.
+
+use std::cmp;
+use std::collections::BTreeMap;
+
+use cube::geometry::Geometry;
+use cube::mesh::{self, MeshBuilder, MeshOptions};
+use cube::space::{LogWidth, Partition, Spatial};
+use cube::tree::{Cube, Node};
+use math::{FPoint3, FScalar, FVector3, IntoSpace, UPoint3, UScalar};
+use resource::ResourceId;
+
+type Index3 = [isize; 3];
+
+/// Pushes a smooth surface for a cube into per-material `MeshBuilder`s.
+///
+/// Samples are spaced by the given width and are taken from the root cube,
+/// including samples just beyond the boundary of the cube. Each cube only
+/// generates quads for the edges that begin at its own samples, so adjacent
+/// cubes (such as chunks) generate matching vertices and do not overlap.
+pub(super) fn push_surface<'r>(
+    root: &'r Cube<'r, &'r Node>,
+    cube: &Cube<&Node>,
+    width: LogWidth,
+    options: &MeshOptions,
+    builders: &mut BTreeMap<ResourceId, MeshBuilder<'r>>,
+) {
+    let width = cmp::min(width, cube.partition().width());
+    let grid = Grid::new(root, cube, width);
+    let n = grid.n;
+    for a in 0..3 {
+        let (b, c) = ((a + 1) % 3, (a + 2) % 3);
+        // Edges at the lower boundary of the cube are owned by the adjacent
+        // cube, unless the cube lies on the lower boundary of the root.
+        let lower = if grid.origin[a] == root.partition().origin()[a] {
+            -1
+        }
+        else {
+            0
+        };
+        for i in lower..n {
+            for j in 0..n {
+                for k in 0..n {
+                    let mut start = [0; 3];
+                    start[a] = i;
+                    start[b] = j;
+                    start[c] = k;
+                    let mut end = start;
+                    end[a] += 1;
+                    let (material, outward) = match (grid.sample(&start), grid.sample(&end)) {
+                        (Some(sample), None) => (sample.material, true),
+                        (None, Some(sample)) => (sample.material, false),
+                        _ => continue,
+                    };
+                    let mut positions = [FPoint3::origin(); 4];
+                    let mut complete = true;
+                    for (position, &(u, v)) in positions
+                        .iter_mut()
+                        .zip([(0, 0), (1, 0), (1, 1), (0, 1)].iter())
+                    {
+                        let mut cell = start;
+                        cell[b] -= u;
+                        cell[c] -= v;
+                        match grid.vertex(&cell) {
+                            Some(vertex) => *position = vertex,
+                            None => complete = false,
+                        }
+                    }
+                    if !complete {
+                        continue;
+                    }
+                    if !outward {
+                        positions.swap(1, 3);
+                    }
+                    builders
+                        .entry(material)
+                        .or_insert_with(|| MeshBuilder::new(root, material, options))
+                        .push_quad(&positions, mesh::orientation(a, outward));
+                }
+            }
+        }
+    }
+}
+
+/// An occupied sample and the leaf that occupies it.
+#[derive(Clone, Copy)]
+struct Sample {
+    partition: Partition,
+    geometry: Geometry,
+    material: ResourceId,
+}
+
+/// Samples of a cube and the vertices of the dual cells between them.
+struct Grid {
+    origin: UPoint3,
+    step: UScalar,
+    /// The number of samples along each axis within the cube.
+    n: isize,
+    /// Samples, including a border of one sample around the cube.
+    samples: Vec<Option<Sample>>,
+}
+
+impl Grid {
+    fn new(root: &Cube<&Node>, cube: &Cube<&Node>, width: LogWidth) -> Self {
+        let origin = *cube.partition().origin();
+        let step = width.exp();
+        let n = (cube.partition().width().exp() / step) as isize;
+        let mut grid = Grid {
+            origin: origin,
+            step: step,
+            n: n,
+            samples: Vec::with_capacity(((n + 2) * (n + 2) * (n + 2)) as usize),
+        };
+        for z in -1..(n + 1) {
+            for y in -1..(n + 1) {
+                for x in -1..(n + 1) {
+                    let sample = grid.point(&[x, y, z])
+                        .and_then(|point| mesh::occupant(root, &point))
+                        .map(|(partition, geometry, material)| Sample {
+                            partition: partition,
+                            geometry: geometry,
+                            material: material,
+                        });
+                    grid.samples.push(sample);
+                }
+            }
+        }
+        grid
+    }
+
+    /// Gets the point at the center of a sample, or `None` if the sample
+    /// lies before the origin of the tree.
+    fn point(&self, index: &Index3) -> Option<UPoint3> {
+        let mut point = self.origin;
+        for axis in 0..3 {
+            let offset = (index[axis] * self.step as isize) + (self.step / 2) as isize;
+            let coordinate = point[axis] as isize + offset;
+            if coordinate < 0 {
+                return None;
+            }
+            point[axis] = coordinate as UScalar;
+        }
+        Some(point)
+    }
+
+    fn sample(&self, index: &Index3) -> Option<Sample> {
+        let m = self.n + 2;
+        if index.iter().any(|x| *x < -1 || *x > self.n) {
+            return None;
+        }
+        let (x, y, z) = (index[0] + 1, index[1] + 1, index[2] + 1);
+        self.samples[(x + (y * m) + (z * m * m)) as usize]
+    }
+
+    /// Gets the position of the center of a sample.
+    fn position(&self, index: &Index3) -> FPoint3 {
+        let origin: FPoint3 = self.origin.into_space();
+        let step = self.step as FScalar;
+        origin
+            + FVector3::new(
+                (index[0] as FScalar + 0.5) * step,
+                (index[1] as FScalar + 0.5) * step,
+                (index[2] as FScalar + 0.5) * step,
+            )
+    }
+
+    /// Gets the point where an edge between an occupied and an empty sample
+    /// crosses the surface.
+    ///
+    /// The crossing lies on the deformed bounds of the leaf that occupies the
+    /// occupied sample, clamped to the edge.
+    fn crossing(&self, start: &Index3, axis: usize) -> Option<FPoint3> {
+        let mut end = *start;
+        end[axis] += 1;
+        let (lower, upper) = (self.position(start), self.position(&end));
+        let (sample, point, outward) = match (self.sample(start), self.sample(&end)) {
+            (Some(sample), None) => (sample, lower, true),
+            (None, Some(sample)) => (sample, upper, false),
+            _ => return None,
+        };
+        let origin: FPoint3 = (*sample.partition.origin()).into_space();
+        let width = sample.partition.width().exp() as FScalar;
+        let unit = FPoint3::origin() + ((point - origin) / width);
+        let (front, back) = sample.geometry.unit_bounds(axis, &unit);
+        let bound = origin[axis] + (width * if outward { back } else { front });
+        let mut crossing = lower;
+        crossing[axis] = bound.max(lower[axis]).min(upper[axis]);
+        Some(crossing)
+    }
+
+    /// Gets the vertex of the dual cell with the given sample at its lower
+    /// corner, or `None` if the surface does not cross the cell.
+    fn vertex(&self, cell: &Index3) -> Option<FPoint3> {
+        let mut sum = FVector3::new(0.0, 0.0, 0.0);
+        let mut count = 0;
+        for axis in 0..3 {
+            let (b, c) = ((axis + 1) % 3, (axis + 2) % 3);
+            for &(u, v) in &[(0, 0), (1, 0), (0, 1), (1, 1)] {
+                let mut start = *cell;
+                start[b] += u;
+                start[c] += v;
+                if let Some(crossing) = self.crossing(&start, axis) {
+                    sum += crossing.coords;
+                    count += 1;
+                }
+            }
+        }
+        if count == 0 {
+            None
+        }
+        else {
+            Some(FPoint3::origin() + (sum / count as FScalar))
+        }
+    }
+}
@@ -47,21 +47,32 @@
 //! }
 //! ```
 
+mod brush;
+mod chunk;
+mod csg;
 mod edit;
 mod geometry;
+mod heightmap;
 mod mesh;
+mod sculpt;
 mod space;
 #[macro_use]
 mod traverse;
 mod tree;
+mod voxelize;
 
-use self::tree::{Cube, OrphanCube};
-
-pub use self::edit::Cursor;
+pub use self::brush::{Brush, Shape};
+pub use self::chunk::MeshCache;
+pub use self::csg::Operation;
+pub use self::edit::{Cursor, Edit};
 pub use self::geometry::{Edge, Geometry, Offset};
+pub use self::mesh::{MeshOptions, Strategy};
+pub use self::sculpt::{DistanceField, SculptMode};
 pub use self::space::{Axis, Direction, Intersects, LogWidth, Orientation, Partition, RayCast,
                       RayIntersection, Spatial, AABB};
-pub use self::tree::{BranchNode, BranchPayload, LeafNode, LeafPayload, Node, OrphanNode, Tree};
+pub use self::tree::{BranchNode, BranchPayload, Cube, LeafNode, LeafPayload, Node, OrphanCube,
+                     OrphanNode, Tree};
+pub use self::voxelize::TriangleMesh;
 
 pub type CubeRef<'a, 'b> = Cube<'a, &'b Node>;
 pub type CubeMut<'a, 'b> = Cube<'a, &'b mut Node>;
@@ -76,4 +87,8 @@ pub enum CubeError {
     JoinLeaf,
     #[fail(display = "attempted to subdivide branch")]
     SubdivideBranch,
+    #[fail(display = "mismatched widths")]
+    WidthMismatch,
+    #[fail(display = "point out of bounds")]
+    OutOfBounds,
 }
@@ -0,0 +1,212 @@
+use cube::geometry::{Edge, Geometry};
+use cube::space::{self, LogWidth, Partition, Spatial};
+use cube::tree::{Cube, Node};
+use math::{FPoint3, FScalar, FVector3, IntoSpace};
+use resource::ResourceId;
+
+/// A signed distance field.
+///
+/// Distances are negative inside of the surface described by the field and
+/// positive outside of it. Fields are used to sculpt the geometry of cubes in
+/// a tree.
+pub trait DistanceField {
+    /// Gets the signed distance from a point to the surface of the field.
+    ///
+    /// The distance must never overestimate the true distance to the surface,
+    /// because it is used to determine if a cube lies entirely inside or
+    /// outside of the surface.
+    fn distance(&self, point: &FPoint3) -> FScalar;
+}
+
+/// Determines how a `DistanceField` modifies the cubes in a tree when
+/// sculpting.
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub enum SculptMode {
+    /// Adds the volume inside of the surface to the tree.
+    Add,
+    /// Subtracts the volume inside of the surface from the tree.
+    Subtract,
+}
+
+impl<'a, N> Cube<'a, N>
+where
+    N: AsRef<Node> + AsMut<Node>,
+{
+    /// Sculpts the cube with a `DistanceField`.
+    ///
+    /// Cubes that lie entirely inside or outside of the surface are filled or
+    /// cleared without further subdivision. Cubes that intersect the surface
+    /// are subdivided to the given width and their `Edge`s are deformed to
+    /// approximate the surface. When adding, the given material is assigned to
+    /// cubes that are filled by the field.
+    ///
+    /// Returns the `Partition`s of the affected cubes.
+    pub fn sculpt<F>(
+        &mut self,
+        field: &F,
+        width: LogWidth,
+        mode: SculptMode,
+        material: ResourceId,
+    ) -> Vec<Partition>
+    where
+        F: DistanceField,
+    {
+        let mut partitions = vec![];
+        sculpt(self, field, width, mode, material, &mut partitions);
+        partitions
+    }
+}
+
+fn sculpt<N, F>(
+    cube: &mut Cube<N>,
+    field: &F,
+    width: LogWidth,
+    mode: SculptMode,
+    material: ResourceId,
+    partitions: &mut Vec<Partition>,
+) where
+    N: AsRef<Node> + AsMut<Node>,
+    F: DistanceField,
+{
+    // Distances are oriented such that the volume in which they are negative
+    // is occupied after sculpting.
+    let distance = |point: &FPoint3| match mode {
+        SculptMode::Add => field.distance(point),
+        SculptMode::Subtract => -field.distance(point),
+    };
+    let partition = *cube.partition();
+    let origin: FPoint3 = (*partition.origin()).into_space();
+    let extent = partition.width().exp() as FScalar;
+    let radius = extent * (3.0 as FScalar).sqrt() * 0.5;
+    let center = distance(&(origin + (FVector3::new(1.0, 1.0, 1.0) * (extent * 0.5))));
+    let (inside, outside) = (center < -radius, center > radius);
+    let unaffected = match mode {
+        SculptMode::Add => {
+            outside || cube.as_leaf().map_or(false, |leaf| leaf.geometry.is_full())
+        }
+        SculptMode::Subtract => {
+            inside || cube.as_leaf().map_or(false, |leaf| leaf.geometry.is_empty())
+        }
+    };
+    if unaffected {
+        return;
+    }
+    if inside || outside {
+        let _ = cube.join();
+        if let Some(leaf) = cube.as_leaf_mut() {
+            match mode {
+                SculptMode::Add => {
+                    leaf.geometry = Geometry::full();
+                    leaf.material = material;
+                }
+                SculptMode::Subtract => {
+                    leaf.geometry = Geometry::empty();
+                }
+            }
+        }
+        partitions.push(partition);
+    }
+    else if cube.is_leaf() && (partition.width() <= width || partition.is_min_width()) {
+        let distances = (0..8)
+            .map(|index| {
+                let unit: FVector3 = space::unit_at_index(index).coords.into_space();
+                distance(&(origin + (unit * extent)))
+            })
+            .collect::<Vec<_>>();
+        let deformation = Geometry::from_edges(|_, front, back| {
+            Edge::from_distances(
+                distances[space::index_at_unit(front)],
+                distances[space::index_at_unit(back)],
+            )
+        });
+        if let Some(leaf) = cube.as_leaf_mut() {
+            match mode {
+                SculptMode::Add => {
+                    if leaf.geometry.is_empty() {
+                        leaf.material = material;
+                    }
+                    leaf.geometry = leaf.geometry.union(&deformation);
+                }
+                SculptMode::Subtract => {
+                    leaf.geometry = leaf.geometry.intersection(&deformation);
+                }
+            }
+        }
+        partitions.push(partition);
+    }
+    else {
+        let _ = cube.subdivide();
+        for index in 0..8 {
+            if let Some(mut cube) = cube.at_index_mut(index) {
+                sculpt(&mut cube, field, width, mode, material, partitions);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use cube::brush::Brush;
+    use cube::space::{Axis, LogWidth};
+    use cube::tree::Tree;
+    use math::{FPoint3, UPoint3};
+
+    use super::*;
+
+    #[test]
+    fn brush_distances_are_signed() {
+        let center = FPoint3::new(8.0, 8.0, 8.0);
+        let sphere = Brush::sphere(&center, 4.0);
+        let cylinder = Brush::cylinder(Axis::Y, &center, 4.0);
+
+        assert!((sphere.distance(&center) + 4.0).abs() < 1.0e-5);
+        assert!((sphere.distance(&FPoint3::new(14.0, 8.0, 8.0)) - 2.0).abs() < 1.0e-5);
+        assert!(cylinder.distance(&FPoint3::new(8.0, 11.0, 8.0)) < 0.0);
+        assert!(cylinder.distance(&FPoint3::new(8.0, 13.0, 8.0)) > 0.0);
+    }
+
+    #[test]
+    fn add_sphere_fills_center() {
+        let mut tree = Tree::empty(LogWidth::new(8));
+        let brush = Brush::sphere(&FPoint3::new(128.0, 128.0, 128.0), 100.0);
+        let partitions = brush.apply(&mut tree.as_cube_mut(), LogWidth::new(4), SculptMode::Add, 3);
+
+        assert!(!partitions.is_empty());
+        let root = tree.as_cube();
+        let center = root.at_point(&UPoint3::new(128, 128, 128), LogWidth::min_value())
+            .unwrap();
+        let leaf = center.as_leaf().unwrap();
+        assert!(leaf.geometry.is_full());
+        assert_eq!(3, leaf.material);
+        let corner = root.at_point(&UPoint3::origin(), LogWidth::min_value()).unwrap();
+        assert!(corner.as_leaf().unwrap().geometry.is_empty());
+    }
+
+    #[test]
+    fn subtract_sphere_clears_center() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let brush = Brush::sphere(&FPoint3::new(128.0, 128.0, 128.0), 100.0);
+        let _ = brush.apply(&mut tree.as_cube_mut(), LogWidth::new(4), SculptMode::Subtract, 0);
+
+        let root = tree.as_cube();
+        let center = root.at_point(&UPoint3::new(128, 128, 128), LogWidth::min_value())
+            .unwrap();
+        assert!(center.as_leaf().unwrap().geometry.is_empty());
+        let corner = root.at_point(&UPoint3::origin(), LogWidth::min_value()).unwrap();
+        assert!(corner.as_leaf().unwrap().geometry.is_full());
+    }
+
+    #[test]
+    fn surface_leaves_are_deformed() {
+        let mut tree = Tree::empty(LogWidth::new(6));
+        let brush = Brush::sphere(&FPoint3::new(0.0, 0.0, 0.0), 40.0);
+        let _ = brush.apply(&mut tree.as_cube_mut(), LogWidth::new(4), SculptMode::Add, 0);
+
+        // Leaves that straddle the surface are partially filled.
+        assert!(tree.as_cube().iter().any(|cube| {
+            cube.as_leaf().map_or(false, |leaf| {
+                !leaf.geometry.is_full() && !leaf.geometry.is_empty()
+            })
+        }));
+    }
+}
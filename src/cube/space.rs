@@ -48,7 +48,7 @@ impl LogWidth {
 /// An axis in the tree space.
 ///
 /// Defines and orders the axes in the tree space.
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, Eq, PartialEq)]
 pub enum Axis {
     X = 0,
     Y = 1,
@@ -82,6 +82,7 @@ impl From<usize> for Axis {
 }
 
 /// A direction along an `Axis`.
+#[derive(Clone, Copy, Eq, PartialEq)]
 pub enum Direction {
     Positive,
     Negative,
@@ -92,6 +93,7 @@ pub enum Direction {
 /// This is akin to choosing a face of a cube in a tree and orienting that face
 /// based on the axis and direction in which it deforms. For example, `Left` is
 /// a face along the `X` axis that deforms in the `Positive` direction.
+#[derive(Clone, Copy, Eq, PartialEq)]
 pub enum Orientation {
     Left,
     Right,
@@ -118,6 +120,18 @@ impl Orientation {
             Orientation::Right | Orientation::Top | Orientation::Front => Direction::Negative,
         }
     }
+
+    /// Gets the `Orientation` of the opposing face along the same `Axis`.
+    pub fn opposite(&self) -> Self {
+        match *self {
+            Orientation::Left => Orientation::Right,
+            Orientation::Right => Orientation::Left,
+            Orientation::Top => Orientation::Bottom,
+            Orientation::Bottom => Orientation::Top,
+            Orientation::Front => Orientation::Back,
+            Orientation::Back => Orientation::Front,
+        }
+    }
 }
 
 pub trait PointNormal {
@@ -415,3 +429,43 @@ pub fn vector_at_index(index: usize, width: LogWidth) -> UVector3 {
         ((index >> 2) & UScalar::one()) * width,
     )
 }
+
+/// Gets the point of the unit cube at a given subdivision index.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub fn unit_at_index(index: usize) -> UPoint3 {
+    assert!(index < 8);
+    let index = index as UScalar;
+    UPoint3::new(
+         index       & UScalar::one(),
+        (index >> 1) & UScalar::one(),
+        (index >> 2) & UScalar::one(),
+    )
+}
+
+/// Gets the subdivision index of a point of the unit cube.
+#[cfg_attr(rustfmt, rustfmt_skip)]
+pub fn index_at_unit(unit: &UPoint3) -> usize {
+    (( unit.x & UScalar::one()      ) |
+     ((unit.y & UScalar::one()) << 1) |
+     ((unit.z & UScalar::one()) << 2)) as usize
+}
+
+/// Rotates a point in a cubic lattice spanning `[0, max]` along each axis by a
+/// positive (right-handed) quarter turn about the given `Axis` through the
+/// center of the lattice.
+pub fn rotate_lattice_point(point: &UPoint3, axis: Axis, max: UScalar) -> UPoint3 {
+    match axis {
+        Axis::X => UPoint3::new(point.x, max - point.z, point.y),
+        Axis::Y => UPoint3::new(point.z, point.y, max - point.x),
+        Axis::Z => UPoint3::new(max - point.y, point.x, point.z),
+    }
+}
+
+/// Reflects a point in a cubic lattice spanning `[0, max]` along each axis
+/// across the plane through the center of the lattice that is orthogonal to
+/// the given `Axis`.
+pub fn reflect_lattice_point(point: &UPoint3, axis: Axis, max: UScalar) -> UPoint3 {
+    let mut point = *point;
+    point[axis as usize] = max - point[axis as usize];
+    point
+}
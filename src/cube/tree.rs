@@ -1,14 +1,16 @@
 use arrayvec::ArrayVec;
 use num::Bounded;
+use std::cell::RefCell;
 use std::convert::{AsMut, AsRef};
+use std::mem;
 use std::ops::{Deref, DerefMut};
 
 use cube::edit::Cursor;
 use cube::geometry::Geometry;
-use cube::space::{self, Intersects, LogWidth, Partition, RayCast, RayIntersection, Spatial};
+use cube::space::{self, Axis, Intersects, LogWidth, Partition, RayCast, RayIntersection, Spatial};
 use cube::traverse::{PathTraversal, Traversal};
 use cube::CubeError;
-use math::{Clamp, FRay3, FScalar, UPoint3};
+use math::{Clamp, FRay3, FScalar, UPoint3, UScalar};
 use resource::ResourceId;
 
 type NodeLink = Box<[Node; 8]>;
@@ -123,6 +125,30 @@ impl Node {
             Err(CubeError::SubdivideBranch)
         }
     }
+
+    /// Transforms the `Node` and its sub-trees by an isometry of the unit cube
+    /// onto itself. See `Geometry::transform`.
+    fn transform<F>(&mut self, f: &F)
+    where
+        F: Fn(&UPoint3, UScalar) -> UPoint3,
+    {
+        match *self {
+            Node::Leaf(ref mut leaf) => {
+                leaf.geometry = leaf.geometry.transform(f);
+            }
+            Node::Branch(ref mut branch) => {
+                let nodes = branch
+                    .nodes
+                    .iter_mut()
+                    .map(|node| mem::replace(node, Node::new()))
+                    .collect::<Vec<_>>();
+                for (index, mut node) in nodes.into_iter().enumerate() {
+                    node.transform(f);
+                    branch.nodes[space::index_at_unit(&f(&space::unit_at_index(index), 1))] = node;
+                }
+            }
+        }
+    }
 }
 
 impl AsRef<Node> for Node {
@@ -350,31 +376,97 @@ impl Hint {
     }
 }
 
+/// The root of a `Tree`, which is shared by all of its cubes.
+///
+/// The root records the `Partition`s of cubes that are mutably accessed, so
+/// that data derived from the tree (such as meshes) can be updated
+/// incrementally.
+struct Root {
+    partition: Partition,
+    touched: RefCell<Vec<Partition>>,
+}
+
+impl Root {
+    fn new(partition: Partition) -> Self {
+        Root {
+            partition: partition,
+            touched: RefCell::new(vec![]),
+        }
+    }
+
+    /// Records a `Partition` as touched.
+    ///
+    /// Touched `Partition`s are coalesced: a `Partition` that lies within a
+    /// touched ancestor is not recorded, and recording a `Partition` discards
+    /// any touched descendants. This bounds the number of touched
+    /// `Partition`s by the number of disjoint cubes that have been touched.
+    fn touch(&self, partition: &Partition) {
+        let contains = |ancestor: &Partition, partition: &Partition| {
+            ancestor.width() >= partition.width() && ancestor.aabb().intersects(partition.origin())
+        };
+        let mut touched = self.touched.borrow_mut();
+        if touched.iter().any(|ancestor| contains(ancestor, partition)) {
+            return;
+        }
+        touched.retain(|descendant| !contains(partition, descendant));
+        touched.push(*partition);
+    }
+}
+
+impl Deref for Root {
+    type Target = Partition;
+
+    fn deref(&self) -> &Self::Target {
+        &self.partition
+    }
+}
+
 pub struct Tree {
     node: Box<Node>,
-    partition: Partition,
+    root: Root,
 }
 
 impl Tree {
     pub fn new(width: LogWidth) -> Self {
         Tree {
             node: Box::new(Node::new()),
-            partition: Partition::at_point(&UPoint3::origin(), width),
+            root: Root::new(Partition::at_point(&UPoint3::origin(), width)),
         }
     }
 
+    /// Constructs a new `Tree` with an empty root.
+    pub fn empty(width: LogWidth) -> Self {
+        let mut tree = Tree::new(width);
+        if let Some(leaf) = tree.node.as_leaf_mut() {
+            leaf.geometry = Geometry::empty();
+        }
+        tree
+    }
+
     pub fn as_cube(&self) -> Cube<&Node> {
-        Cube::new(&self.node, &self.partition, self.partition)
+        Cube::new(&self.node, &self.root, self.root.partition)
     }
 
     pub fn as_cube_mut(&mut self) -> Cube<&mut Node> {
-        Cube::new(&mut self.node, &self.partition, self.partition)
+        Cube::new(&mut self.node, &self.root, self.root.partition)
+    }
+
+    /// Takes the `Partition`s of the cubes that have been modified since the
+    /// last call to `take_touched`.
+    ///
+    /// A cube is considered modified when its node or payload is mutably
+    /// accessed or it is subdivided or joined, so this may include cubes that
+    /// did not actually change. Traversing into the subdivisions of a cube
+    /// does not modify it. `Partition`s are disjoint; cubes within a modified
+    /// ancestor are not included.
+    pub fn take_touched(&mut self) -> Vec<Partition> {
+        mem::replace(&mut *self.root.touched.borrow_mut(), vec![])
     }
 }
 
 impl Spatial for Tree {
     fn partition(&self) -> &Partition {
-        &self.partition
+        &self.root.partition
     }
 
     fn depth(&self) -> u8 {
@@ -387,7 +479,7 @@ where
     N: AsRef<Node>,
 {
     node: N,
-    root: &'a Partition,
+    root: &'a Root,
     partition: Partition,
 }
 
@@ -395,7 +487,7 @@ impl<'a, N> Cube<'a, N>
 where
     N: AsRef<Node>,
 {
-    fn new(node: N, root: &'a Partition, partition: Partition) -> Self {
+    fn new(node: N, root: &'a Root, partition: Partition) -> Self {
         Cube {
             node: node,
             root: root,
@@ -492,8 +584,17 @@ where
         }
     }
 
+    /// Copies the cube and its sub-trees into a new `Tree`. The root of the
+    /// `Tree` has the same width as the cube, but is located at the origin.
+    pub fn to_tree(&self) -> Tree {
+        Tree {
+            node: Box::new(self.node.as_ref().clone()),
+            root: Root::new(Partition::at_point(&UPoint3::origin(), self.partition.width())),
+        }
+    }
+
     /// Resolves the type parameter `N` to `&Node`.
-    fn with_node_ref(&self) -> Cube<&Node> {
+    pub(super) fn with_node_ref(&self) -> Cube<&Node> {
         Cube::new(self.node.as_ref(), self.root, self.partition)
     }
 }
@@ -571,7 +672,7 @@ where
     }
 
     pub fn at_point_mut(&mut self, point: &UPoint3, width: LogWidth) -> Option<Cube<&mut Node>> {
-        self.for_each_node_to_point(point, width, |_| {})
+        self.for_each_node_to_point(point, width, |_, _| {})
     }
 
     pub fn at_index_mut(&mut self, index: usize) -> Option<Cube<&mut Node>> {
@@ -613,7 +714,9 @@ where
     }
 
     pub fn join(&mut self) -> Result<(), CubeError> {
-        self.node.as_mut().join()
+        self.node.as_mut().join()?;
+        self.root.touch(&self.partition);
+        Ok(())
     }
 
     pub fn subdivide(&mut self) -> Result<(), CubeError> {
@@ -621,7 +724,9 @@ where
             Err(CubeError::LimitExceeded)
         }
         else {
-            self.node.as_mut().subdivide()
+            self.node.as_mut().subdivide()?;
+            self.root.touch(&self.partition);
+            Ok(())
         }
     }
 
@@ -630,8 +735,11 @@ where
         point: &UPoint3,
         width: LogWidth,
     ) -> Option<Cube<&mut Node>> {
-        self.for_each_node_to_point(point, width, |node| {
-            let _ = node.subdivide();
+        let root = self.root;
+        self.for_each_node_to_point(point, width, |node, partition| {
+            if node.subdivide().is_ok() {
+                root.touch(partition);
+            }
         })
     }
 
@@ -643,7 +751,7 @@ where
                     cubes.push(traversal.take());
                 }
                 else if traversal.peek().partition.width() > cursor.width() {
-                    let _ = traversal.peek_mut().node.as_mut().subdivide();
+                    let _ = traversal.peek_mut().subdivide();
                     traversal.push();
                 }
             }
@@ -651,6 +759,39 @@ where
         cubes
     }
 
+    /// Rotates the cube and its sub-trees in place by a positive (right-handed)
+    /// quarter turn about the given `Axis` through its midpoint.
+    pub fn rotate(&mut self, axis: Axis) {
+        self.root.touch(&self.partition);
+        self.node
+            .as_mut()
+            .transform(&|point: &UPoint3, max| space::rotate_lattice_point(point, axis, max));
+    }
+
+    /// Reflects (mirrors) the cube and its sub-trees in place across the plane
+    /// through its midpoint that is orthogonal to the given `Axis`.
+    pub fn reflect(&mut self, axis: Axis) {
+        self.root.touch(&self.partition);
+        self.node
+            .as_mut()
+            .transform(&|point: &UPoint3, max| space::reflect_lattice_point(point, axis, max));
+    }
+
+    /// Replaces the cube and its sub-trees with a copy of the given `Tree`.
+    /// This can be used to place a sub-tree extracted with `to_tree`.
+    ///
+    /// Fails if the width of the `Tree` differs from the width of the cube.
+    pub fn graft(&mut self, tree: &Tree) -> Result<(), CubeError> {
+        if self.partition.width() == tree.root.width() {
+            self.root.touch(&self.partition);
+            *self.node.as_mut() = (*tree.node).clone();
+            Ok(())
+        }
+        else {
+            Err(CubeError::WidthMismatch)
+        }
+    }
+
     #[allow(dead_code)]
     fn instrument(&mut self) -> usize {
         self.for_each_path_mut(|(cube, path)| {
@@ -674,7 +815,7 @@ where
         mut f: F,
     ) -> Option<Cube<&mut Node>>
     where
-        F: FnMut(&mut Node),
+        F: FnMut(&mut Node, &Partition),
     {
         if self.partition.aabb().intersects(point) {
             let mut node: Option<&mut Node> = Some(self.node.as_mut());
@@ -684,7 +825,7 @@ where
             let width = width.clamp(LogWidth::min_value(), depth);
             while width < depth {
                 let taken = node.take().unwrap();
-                f(taken);
+                f(taken, &Partition::at_point(&point, depth));
                 match *taken {
                     Node::Branch(ref mut branch) => {
                         depth = depth - 1;
@@ -772,6 +913,7 @@ where
     N: AsRef<Node> + AsMut<Node>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.root.touch(&self.partition);
         self.node.as_mut()
     }
 }
@@ -883,7 +1025,7 @@ where
     B: AsRef<BranchPayload>,
 {
     node: OrphanNode<L, B>,
-    root: &'a Partition,
+    root: &'a Root,
     partition: Partition,
 }
 
@@ -892,7 +1034,7 @@ where
     L: AsRef<LeafPayload>,
     B: AsRef<BranchPayload>,
 {
-    fn new(node: OrphanNode<L, B>, root: &'a Partition, partition: Partition) -> Self {
+    fn new(node: OrphanNode<L, B>, root: &'a Root, partition: Partition) -> Self {
         OrphanCube {
             node: node,
             root: root,
@@ -919,6 +1061,7 @@ where
     B: AsRef<BranchPayload> + AsMut<BranchPayload>,
 {
     fn deref_mut(&mut self) -> &mut Self::Target {
+        self.root.touch(&self.partition);
         &mut self.node
     }
 }
@@ -936,3 +1079,121 @@ where
         self.root.width().to_inner() - self.partition.width().to_inner()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate_permutes_subdivisions() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().subdivide().unwrap();
+        tree.as_cube_mut()
+            .at_index_mut(1)
+            .unwrap()
+            .as_leaf_mut()
+            .unwrap()
+            .geometry = Geometry::empty();
+        tree.as_cube_mut().rotate(Axis::Z);
+
+        assert!(!tree.as_cube().at_index(1).unwrap().is_empty());
+        assert!(tree.as_cube().at_index(3).unwrap().is_empty());
+    }
+
+    #[test]
+    fn reflect_twice_is_identity() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().subdivide().unwrap();
+        tree.as_cube_mut()
+            .at_index_mut(6)
+            .unwrap()
+            .as_leaf_mut()
+            .unwrap()
+            .geometry = Geometry::empty();
+        let mut subtree = tree.as_cube().to_tree();
+        subtree.as_cube_mut().reflect(Axis::Y);
+
+        assert!(subtree.as_cube().at_index(4).unwrap().is_empty());
+
+        subtree.as_cube_mut().reflect(Axis::Y);
+        tree.as_cube_mut().graft(&subtree).unwrap();
+
+        assert!(tree.as_cube().at_index(6).unwrap().is_empty());
+    }
+
+    #[test]
+    fn take_touched_records_mutated_cubes() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        tree.as_cube_mut().subdivide().unwrap();
+        let _ = tree.take_touched();
+        tree.as_cube_mut()
+            .at_index_mut(5)
+            .unwrap()
+            .as_leaf_mut()
+            .unwrap()
+            .geometry = Geometry::empty();
+        let touched = tree.take_touched();
+
+        assert_eq!(1, touched.len());
+        assert!(touched[0].width() == LogWidth::new(7));
+        assert!(tree.take_touched().is_empty());
+    }
+
+    #[test]
+    fn take_touched_coalesces_descendants() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.take_touched();
+        let _ = tree.as_cube_mut()
+            .subdivide_to_point(&UPoint3::origin(), LogWidth::new(4));
+        tree.as_cube_mut()
+            .at_point_mut(&UPoint3::origin(), LogWidth::new(4))
+            .unwrap()
+            .as_leaf_mut()
+            .unwrap()
+            .geometry = Geometry::empty();
+        let touched = tree.take_touched();
+
+        // Subdividing the root touches it, which covers all of its
+        // descendants.
+        assert_eq!(1, touched.len());
+        assert!(touched[0].width() == LogWidth::new(8));
+
+        tree.as_cube_mut()
+            .at_index_mut(7)
+            .unwrap()
+            .subdivide()
+            .unwrap();
+        tree.as_cube_mut()
+            .at_point_mut(&UPoint3::new(255, 255, 255), LogWidth::new(6))
+            .unwrap()
+            .as_leaf_mut()
+            .unwrap()
+            .geometry = Geometry::empty();
+        let touched = tree.take_touched();
+
+        assert_eq!(1, touched.len());
+        assert!(touched[0].width() == LogWidth::new(7));
+    }
+
+    #[test]
+    fn traversal_does_not_touch_cubes() {
+        use cube::Edit;
+
+        let mut tree = Tree::new(LogWidth::new(8));
+        let _ = tree.as_cube_mut()
+            .subdivide_to_point(&UPoint3::origin(), LogWidth::new(4));
+        let _ = tree.take_touched();
+        for _ in tree.as_cube_mut().iter_mut() {}
+        assert!(tree.take_touched().is_empty());
+
+        // Only the edited leaf is touched, not the cubes traversed to reach
+        // it.
+        let cursor = Cursor::at_point(&UPoint3::origin(), LogWidth::new(4));
+        let _ = tree.as_cube_mut().apply(&cursor, &Edit::Clear);
+        let touched = tree.take_touched();
+
+        assert_eq!(1, touched.len());
+        assert!(touched[0].width() == LogWidth::new(4));
+        assert_eq!(UPoint3::origin(), *touched[0].origin());
+    }
+}
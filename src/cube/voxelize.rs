@@ -0,0 +1,266 @@
+use num::Float;
+use plexus::buffer::MeshBuffer;
+use std::cmp;
+
+use cube::sculpt::{DistanceField, SculptMode};
+use cube::space::LogWidth;
+use cube::tree::Tree;
+use math::{self, FPoint3, FScalar, FVector3};
+use render::{Index, Vertex};
+use resource::ResourceId;
+
+/// Oblique directions of the rays used to determine if a point lies inside of
+/// a `TriangleMesh`.
+const RAYS: [[FScalar; 3]; 3] = [
+    [1.0, 0.0137, 0.0071],
+    [0.0113, 1.0, 0.0191],
+    [0.0173, 0.0059, 1.0],
+];
+
+/// A closed triangle mesh.
+///
+/// `TriangleMesh`es are signed distance fields and can be voxelized into a
+/// tree. Because the sign of the distance is determined by counting the
+/// intersections of a ray with the mesh, the mesh should be closed
+/// (watertight).
+///
+/// Computing the distance to a `TriangleMesh` requires visiting every
+/// triangle, so voxelizing large meshes at fine widths can be slow.
+pub struct TriangleMesh {
+    triangles: Vec<[FPoint3; 3]>,
+}
+
+impl TriangleMesh {
+    /// Constructs a `TriangleMesh` from positions and a triangle index buffer.
+    ///
+    /// # Panics
+    ///
+    /// Panics if an index is out of bounds.
+    pub fn new(positions: &[FPoint3], indices: &[Index]) -> Self {
+        TriangleMesh {
+            triangles: indices
+                .chunks(3)
+                .filter(|triangle| triangle.len() == 3)
+                .map(|triangle| {
+                    [
+                        positions[triangle[0] as usize],
+                        positions[triangle[1] as usize],
+                        positions[triangle[2] as usize],
+                    ]
+                })
+                .collect(),
+        }
+    }
+
+    /// Constructs a `TriangleMesh` from the triangles in a `MeshBuffer`.
+    pub fn from_mesh_buffer(buffer: &MeshBuffer<Index, Vertex>) -> Self {
+        let positions = buffer
+            .as_vertex_slice()
+            .iter()
+            .map(|vertex| {
+                FPoint3::new(vertex.position[0], vertex.position[1], vertex.position[2])
+            })
+            .collect::<Vec<_>>();
+        TriangleMesh::new(&positions, buffer.as_index_slice())
+    }
+
+    /// Gets the upper bound of the mesh along each axis.
+    fn upper_bound(&self) -> FPoint3 {
+        let mut bound = FPoint3::origin();
+        for triangle in &self.triangles {
+            for point in triangle.iter() {
+                for axis in 0..3 {
+                    bound[axis] = math::partial_max(bound[axis], point[axis]);
+                }
+            }
+        }
+        bound
+    }
+
+    /// Counts the triangles that intersect a ray.
+    fn intersections(&self, origin: &FPoint3, direction: &FVector3) -> usize {
+        self.triangles
+            .iter()
+            .filter(|triangle| intersects(origin, direction, triangle))
+            .count()
+    }
+}
+
+impl DistanceField for TriangleMesh {
+    fn distance(&self, point: &FPoint3) -> FScalar {
+        let distance = self.triangles
+            .iter()
+            .map(|triangle| (closest_point(point, triangle) - *point).norm())
+            .fold(FScalar::max_value(), math::partial_min);
+        // Rays that pass through the shared edges or vertices of triangles
+        // may count a single crossing more than once. Use a majority vote of
+        // rays cast in oblique directions, which are unlikely to all graze
+        // the mesh.
+        let votes = RAYS
+            .iter()
+            .filter(|direction| {
+                let direction = FVector3::new(direction[0], direction[1], direction[2]);
+                self.intersections(point, &direction.normalize()) % 2 == 1
+            })
+            .count();
+        if votes * 2 > RAYS.len() {
+            -distance
+        }
+        else {
+            distance
+        }
+    }
+}
+
+impl Tree {
+    /// Voxelizes a `TriangleMesh` into a new `Tree`.
+    ///
+    /// Cubes inside of the mesh are full and cubes outside of the mesh are
+    /// empty. Cubes along the surface of the mesh are subdivided to the given
+    /// width and deformed to approximate the surface, and are assigned the
+    /// given material.
+    ///
+    /// The width of the `Tree` is the smallest width that contains the mesh.
+    /// The mesh should lie in the positive octant; any volume at negative
+    /// coordinates is discarded.
+    pub fn from_triangle_mesh(mesh: &TriangleMesh, width: LogWidth, material: ResourceId) -> Self {
+        let bound = mesh.upper_bound();
+        let extent = math::partial_max(math::partial_max(bound.x, bound.y), bound.z);
+        let root = LogWidth::new(cmp::max(
+            width.to_inner(),
+            (extent.max(1.0).log2().floor() as u8) + 1,
+        ));
+        let mut tree = Tree::empty(root);
+        tree.as_cube_mut().sculpt(mesh, width, SculptMode::Add, material);
+        tree
+    }
+}
+
+/// Gets the closest point on a triangle to a given point.
+fn closest_point(point: &FPoint3, triangle: &[FPoint3; 3]) -> FPoint3 {
+    let (a, b, c) = (triangle[0], triangle[1], triangle[2]);
+    let ab = b - a;
+    let ac = c - a;
+    let ap = *point - a;
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+    let bp = *point - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+    let vc = (d1 * d4) - (d3 * d2);
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + (ab * (d1 / (d1 - d3)));
+    }
+    let cp = *point - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+    let vb = (d5 * d2) - (d1 * d6);
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + (ac * (d2 / (d2 - d6)));
+    }
+    let va = (d3 * d6) - (d5 * d4);
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + ((c - b) * ((d4 - d3) / ((d4 - d3) + (d5 - d6))));
+    }
+    let d = 1.0 / (va + vb + vc);
+    a + (ab * (vb * d)) + (ac * (vc * d))
+}
+
+/// Determines if a ray intersects a triangle in front of its origin.
+fn intersects(origin: &FPoint3, direction: &FVector3, triangle: &[FPoint3; 3]) -> bool {
+    let ab = triangle[1] - triangle[0];
+    let ac = triangle[2] - triangle[0];
+    let p = direction.cross(&ac);
+    let determinant = ab.dot(&p);
+    if determinant.abs() <= FScalar::epsilon() {
+        return false;
+    }
+    let inverse = 1.0 / determinant;
+    let t = *origin - triangle[0];
+    let u = t.dot(&p) * inverse;
+    if u < 0.0 || u > 1.0 {
+        return false;
+    }
+    let q = t.cross(&ab);
+    let v = direction.dot(&q) * inverse;
+    if v < 0.0 || (u + v) > 1.0 {
+        return false;
+    }
+    (ac.dot(&q) * inverse) > 0.0
+}
+
+#[cfg(test)]
+mod tests {
+    use cube::space::{LogWidth, Spatial};
+    use math::UPoint3;
+
+    use super::*;
+
+    /// Gets a closed mesh of an axis-aligned box.
+    fn cuboid(lower: FScalar, upper: FScalar) -> TriangleMesh {
+        let positions = (0..8)
+            .map(|index| {
+                let coordinate = |bit| if index & bit == 0 { lower } else { upper };
+                FPoint3::new(coordinate(1), coordinate(2), coordinate(4))
+            })
+            .collect::<Vec<_>>();
+        #[cfg_attr(rustfmt, rustfmt_skip)]
+        let indices = [
+            0, 2, 6, 0, 6, 4, // Left.
+            1, 5, 7, 1, 7, 3, // Right.
+            0, 4, 5, 0, 5, 1, // Bottom.
+            2, 3, 7, 2, 7, 6, // Top.
+            0, 1, 3, 0, 3, 2, // Back.
+            4, 6, 7, 4, 7, 5, // Front.
+        ];
+        TriangleMesh::new(&positions, &indices)
+    }
+
+    #[test]
+    fn closed_mesh_is_signed() {
+        let mesh = cuboid(8.0, 24.0);
+
+        assert!((mesh.distance(&FPoint3::new(16.0, 16.0, 16.0)) + 8.0).abs() < 1.0e-4);
+        assert!((mesh.distance(&FPoint3::new(30.0, 16.0, 16.0)) - 6.0).abs() < 1.0e-4);
+        assert!(mesh.distance(&FPoint3::new(0.0, 0.0, 0.0)) > 0.0);
+    }
+
+    #[test]
+    fn grazing_rays_do_not_flip_sign() {
+        let mesh = cuboid(8.0, 24.0);
+
+        // The first ray from this point enters the box through the diagonal
+        // shared by the two triangles of its left face.
+        let direction = FVector3::new(1.0, 0.0137, 0.0071).normalize();
+        let offset = direction * (8.0 / direction.x);
+        let point = FPoint3::new(0.0, 16.0 - offset.y, 16.0 - offset.z);
+        assert!(mesh.distance(&point) > 0.0);
+        // Points level with a face of the box are outside of it.
+        assert!(mesh.distance(&FPoint3::new(0.0, 24.0, 16.0)) > 0.0);
+        assert!(mesh.distance(&FPoint3::new(0.0, 8.0, 8.0)) > 0.0);
+    }
+
+    #[test]
+    fn voxelize_fills_interior() {
+        let tree = Tree::from_triangle_mesh(&cuboid(20.0, 100.0), LogWidth::new(4), 2);
+
+        assert!(tree.as_cube().partition().width() == LogWidth::new(7));
+        let root = tree.as_cube();
+        let inside = root.at_point(&UPoint3::new(64, 64, 64), LogWidth::min_value())
+            .unwrap();
+        let leaf = inside.as_leaf().unwrap();
+        assert!(leaf.geometry.is_full());
+        assert_eq!(2, leaf.material);
+        let outside = root.at_point(&UPoint3::origin(), LogWidth::min_value()).unwrap();
+        assert!(outside.as_leaf().unwrap().geometry.is_empty());
+    }
+}
@@ -0,0 +1,311 @@
+//! Binary glTF 2.0 (`.glb`) export.
+//!
+//! Meshes are written as a single glTF mesh with one primitive per material.
+//! Primitives include positions, texture coordinates, and colors. Materials
+//! reference textures by path using a mapping provided by the caller;
+//! materials without a texture are written with a white base color.
+
+use failure::Error;
+use plexus::buffer::MeshBuffer;
+use std::collections::HashMap;
+use std::io::Write;
+
+use format;
+use render::{Index, Vertex};
+use resource::ResourceId;
+
+const MAGIC: u32 = 0x4654_6c67;
+const VERSION: u32 = 2;
+const JSON_CHUNK: u32 = 0x4e4f_534a;
+const BIN_CHUNK: u32 = 0x004e_4942;
+
+const ARRAY_BUFFER: u32 = 34_962;
+const ELEMENT_ARRAY_BUFFER: u32 = 34_963;
+const FLOAT: u32 = 5126;
+const UNSIGNED_INT: u32 = 5125;
+const REPEAT: u32 = 10_497;
+
+/// Writes meshes grouped by material as a binary glTF file.
+///
+/// `textures` maps materials to the paths of their textures. Paths are
+/// written as URIs relative to the exported file.
+pub fn write<'a, W, I>(
+    writer: &mut W,
+    buffers: I,
+    textures: &HashMap<ResourceId, String>,
+) -> Result<(), Error>
+where
+    W: Write,
+    I: IntoIterator<Item = (&'a ResourceId, &'a MeshBuffer<Index, Vertex>)>,
+{
+    let mut builder = Builder::default();
+    let mut primitives = vec![];
+    let mut materials = vec![];
+    let mut images = vec![];
+    for (material, buffer) in buffers {
+        let (vertices, indices) = (buffer.as_vertex_slice(), buffer.as_index_slice());
+        if vertices.is_empty() || indices.is_empty() {
+            continue;
+        }
+        let mut min = vertices[0].position;
+        let mut max = vertices[0].position;
+        for vertex in vertices {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(vertex.position[axis]);
+                max[axis] = max[axis].max(vertex.position[axis]);
+            }
+        }
+        let bounds = format!(
+            r#","min":[{},{},{}],"max":[{},{},{}]"#,
+            min[0],
+            min[1],
+            min[2],
+            max[0],
+            max[1],
+            max[2]
+        );
+        let position = builder.push_floats(
+            vertices.iter().flat_map(|vertex| vertex.position.iter().cloned()),
+            "VEC3",
+            &bounds,
+        )?;
+        let uv = builder.push_floats(
+            vertices.iter().flat_map(|vertex| vertex.uv.iter().cloned()),
+            "VEC2",
+            "",
+        )?;
+        let color = builder.push_floats(
+            vertices.iter().flat_map(|vertex| vertex.color.iter().cloned()),
+            "VEC4",
+            "",
+        )?;
+        let indices = builder.push_indices(indices)?;
+        primitives.push(format!(
+            concat!(
+                r#"{{"attributes":{{"POSITION":{},"TEXCOORD_0":{},"COLOR_0":{}}},"#,
+                r#""indices":{},"material":{}}}"#
+            ),
+            position,
+            uv,
+            color,
+            indices,
+            materials.len()
+        ));
+        let name = format::material_name(*material);
+        materials.push(if let Some(path) = textures.get(material) {
+            images.push(format!(r#"{{"uri":"{}"}}"#, escape(path)));
+            format!(
+                concat!(
+                    r#"{{"name":"{}","pbrMetallicRoughness":"#,
+                    r#"{{"baseColorTexture":{{"index":{}}},"metallicFactor":0}}}}"#
+                ),
+                name,
+                images.len() - 1
+            )
+        }
+        else {
+            format!(
+                r#"{{"name":"{}","pbrMetallicRoughness":{{"metallicFactor":0}}}}"#,
+                name
+            )
+        });
+    }
+
+    let mut properties = vec![r#""asset":{"version":"2.0","generator":"bismuth"}"#.to_owned()];
+    if primitives.is_empty() {
+        properties.push(r#""scene":0,"scenes":[{}]"#.to_owned());
+    }
+    else {
+        properties.push(r#""scene":0,"scenes":[{"nodes":[0]}],"nodes":[{"mesh":0}]"#.to_owned());
+        properties.push(format!(r#""meshes":[{{"primitives":[{}]}}]"#, primitives.join(",")));
+        properties.push(format!(r#""materials":[{}]"#, materials.join(",")));
+        properties.push(format!(r#""accessors":[{}]"#, builder.accessors.join(",")));
+        properties.push(format!(r#""bufferViews":[{}]"#, builder.views.join(",")));
+        properties.push(format!(r#""buffers":[{{"byteLength":{}}}]"#, builder.data.len()));
+    }
+    if !images.is_empty() {
+        let textures = (0..images.len())
+            .map(|index| format!(r#"{{"sampler":0,"source":{}}}"#, index))
+            .collect::<Vec<_>>();
+        properties.push(format!(r#""images":[{}]"#, images.join(",")));
+        properties.push(format!(r#""textures":[{}]"#, textures.join(",")));
+        properties.push(format!(
+            r#""samplers":[{{"wrapS":{},"wrapT":{}}}]"#,
+            REPEAT,
+            REPEAT
+        ));
+    }
+
+    // Chunks must be aligned to four bytes. JSON is padded with spaces and
+    // binary data is padded with zeros.
+    let mut json = format!("{{{}}}", properties.join(",")).into_bytes();
+    pad(&mut json, b' ');
+    let mut data = builder.data;
+    pad(&mut data, 0);
+    let mut length = 12 + 8 + json.len();
+    if !data.is_empty() {
+        length += 8 + data.len();
+    }
+
+    format::write_u32(writer, MAGIC)?;
+    format::write_u32(writer, VERSION)?;
+    format::write_u32(writer, length as u32)?;
+    format::write_u32(writer, json.len() as u32)?;
+    format::write_u32(writer, JSON_CHUNK)?;
+    writer.write_all(&json)?;
+    if !data.is_empty() {
+        format::write_u32(writer, data.len() as u32)?;
+        format::write_u32(writer, BIN_CHUNK)?;
+        writer.write_all(&data)?;
+    }
+    Ok(())
+}
+
+/// Accumulates binary data along with the buffer views and accessors that
+/// describe it.
+#[derive(Default)]
+struct Builder {
+    data: Vec<u8>,
+    views: Vec<String>,
+    accessors: Vec<String>,
+}
+
+impl Builder {
+    /// Pushes floating point vertex attributes and returns the index of
+    /// their accessor.
+    fn push_floats<I>(&mut self, values: I, kind: &str, bounds: &str) -> Result<usize, Error>
+    where
+        I: IntoIterator<Item = f32>,
+    {
+        let offset = self.data.len();
+        let mut count = 0;
+        for value in values {
+            format::write_f32(&mut self.data, value)?;
+            count += 1;
+        }
+        let components = match kind {
+            "VEC2" => 2,
+            "VEC3" => 3,
+            _ => 4,
+        };
+        let view = self.push_view(offset, ARRAY_BUFFER);
+        self.accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{},"count":{},"type":"{}"{}}}"#,
+            view,
+            FLOAT,
+            count / components,
+            kind,
+            bounds
+        ));
+        Ok(self.accessors.len() - 1)
+    }
+
+    /// Pushes triangle indices and returns the index of their accessor.
+    fn push_indices(&mut self, indices: &[Index]) -> Result<usize, Error> {
+        let offset = self.data.len();
+        for index in indices {
+            format::write_u32(&mut self.data, *index)?;
+        }
+        let view = self.push_view(offset, ELEMENT_ARRAY_BUFFER);
+        self.accessors.push(format!(
+            r#"{{"bufferView":{},"componentType":{},"count":{},"type":"SCALAR"}}"#,
+            view,
+            UNSIGNED_INT,
+            indices.len()
+        ));
+        Ok(self.accessors.len() - 1)
+    }
+
+    fn push_view(&mut self, offset: usize, target: u32) -> usize {
+        self.views.push(format!(
+            r#"{{"buffer":0,"byteOffset":{},"byteLength":{},"target":{}}}"#,
+            offset,
+            self.data.len() - offset,
+            target
+        ));
+        self.views.len() - 1
+    }
+}
+
+fn pad(bytes: &mut Vec<u8>, value: u8) {
+    while bytes.len() % 4 != 0 {
+        bytes.push(value);
+    }
+}
+
+/// Escapes a string for use in JSON.
+fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '"' => escaped.push_str("\\\""),
+            '\\' => escaped.push_str("\\\\"),
+            c if (c as u32) < 0x20 => escaped.push_str(&format!("\\u{:04x}", c as u32)),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use cube::{LogWidth, Tree};
+    use render::ToMeshBuffer;
+
+    use super::*;
+
+    fn read_u32(data: &[u8], offset: usize) -> usize {
+        data[offset..(offset + 4)]
+            .iter()
+            .rev()
+            .fold(0, |value, byte| (value << 8) | *byte as usize)
+    }
+
+    #[test]
+    fn write_buffers_and_accessors() {
+        let mut tree = Tree::new(LogWidth::new(8));
+        {
+            let mut cube = tree.as_cube_mut();
+            let _ = cube.subdivide();
+            if let Some(mut cube) = cube.at_index_mut(0) {
+                if let Some(leaf) = cube.as_leaf_mut() {
+                    leaf.material = 1;
+                }
+            }
+        }
+        let buffers = tree.as_cube().to_mesh_buffers();
+        let mut textures = HashMap::new();
+        textures.insert(1, "stone.png".to_owned());
+        let mut data = vec![];
+        write(&mut data, &buffers, &textures).unwrap();
+
+        assert_eq!(MAGIC as usize, read_u32(&data, 0));
+        assert_eq!(data.len(), read_u32(&data, 8));
+        let length = read_u32(&data, 12);
+        assert_eq!(JSON_CHUNK as usize, read_u32(&data, 16));
+        let json = String::from_utf8(data[20..(20 + length)].to_vec()).unwrap();
+        let bin = 20 + length;
+        assert_eq!(BIN_CHUNK as usize, read_u32(&data, bin + 4));
+
+        // Each vertex has a position, texture coordinate, and color, and each
+        // index is a four byte integer.
+        let mut bytes = 0;
+        for buffer in buffers.values() {
+            let (vertices, indices) = (buffer.as_vertex_slice(), buffer.as_index_slice());
+            bytes += (vertices.len() * (3 + 2 + 4) * 4) + (indices.len() * 4);
+            for kind in &["VEC3", "VEC2", "VEC4"] {
+                assert!(json.contains(&format!(
+                    r#""count":{},"type":"{}""#,
+                    vertices.len(),
+                    kind
+                )));
+            }
+            assert!(json.contains(&format!(r#""count":{},"type":"SCALAR""#, indices.len())));
+        }
+        assert_eq!(bytes, read_u32(&data, bin));
+        assert_eq!(data.len(), bin + 8 + bytes);
+        assert!(json.contains(&format!(r#""buffers":[{{"byteLength":{}}}]"#, bytes)));
+        assert_eq!(buffers.len(), json.matches(r#""POSITION""#).count());
+        assert!(json.contains(r#""images":[{"uri":"stone.png"}]"#));
+    }
+}
@@ -0,0 +1,116 @@
+//! This module provides import and export of external formats.
+//!
+//! Mesh exporters write `MeshBuffer`s grouped by material, such as those
+//! produced by `ToMeshBuffer::to_mesh_buffers`, so that worlds can be
+//! inspected in other tools. Meshes are expected to be triangulated.
+//!
+//! # Examples
+//!
+//! Exporting a tree as a Wavefront OBJ file and its material library:
+//!
+//! ```rust,no_run
+//! use bismuth::cube::{LogWidth, Tree};
+//! use bismuth::format::obj;
+//! use bismuth::render::ToMeshBuffer;
+//! use std::collections::HashMap;
+//! use std::fs::File;
+//!
+//! let tree = Tree::new(LogWidth::max_value());
+//! let buffers = tree.as_cube().to_mesh_buffers();
+//! let mut file = File::create("world.obj").unwrap();
+//! obj::write(&mut file, "world.mtl", &buffers).unwrap();
+//! let mut file = File::create("world.mtl").unwrap();
+//! obj::write_materials(&mut file, buffers.keys(), &HashMap::new()).unwrap();
+//! ```
+
+use plexus::buffer::MeshBuffer;
+use std::io::{self, Write};
+
+use math::{FPoint3, FVector3};
+use render::{Index, Vertex};
+use resource::ResourceId;
+
+pub mod gltf;
+pub mod obj;
+pub mod ply;
+pub mod stl;
+pub mod vox;
+
+#[derive(Debug, Fail)]
+pub enum FormatError {
+    #[fail(display = "unrecognized format")]
+    UnrecognizedFormat,
+    #[fail(display = "malformed data")]
+    Malformed,
+    #[fail(display = "model too large")]
+    TooLarge,
+}
+
+/// Gets the name used for a material in exported files.
+fn material_name(material: ResourceId) -> String {
+    format!("material{}", material)
+}
+
+/// Gets the triangles of a `MeshBuffer` as triplets of vertices.
+fn triangles<'a>(buffer: &'a MeshBuffer<Index, Vertex>) -> Vec<[&'a Vertex; 3]> {
+    let vertices = buffer.as_vertex_slice();
+    buffer
+        .as_index_slice()
+        .chunks(3)
+        .filter(|indices| indices.len() == 3)
+        .map(|indices| {
+            [
+                &vertices[indices[0] as usize],
+                &vertices[indices[1] as usize],
+                &vertices[indices[2] as usize],
+            ]
+        })
+        .collect()
+}
+
+fn position(vertex: &Vertex) -> FPoint3 {
+    FPoint3::new(vertex.position[0], vertex.position[1], vertex.position[2])
+}
+
+/// Gets the unit normal of a triangle with counter-clockwise winding.
+fn normal(triangle: &[&Vertex; 3]) -> FVector3 {
+    let (a, b, c) = (
+        position(triangle[0]),
+        position(triangle[1]),
+        position(triangle[2]),
+    );
+    let normal = (b - a).cross(&(c - a));
+    let norm = normal.norm();
+    if norm > 0.0 {
+        normal / norm
+    }
+    else {
+        FVector3::new(0.0, 0.0, 0.0)
+    }
+}
+
+fn write_u16<W>(writer: &mut W, value: u16) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_all(&[value as u8, (value >> 8) as u8])
+}
+
+fn write_u32<W>(writer: &mut W, value: u32) -> io::Result<()>
+where
+    W: Write,
+{
+    writer.write_all(&[
+        value as u8,
+        (value >> 8) as u8,
+        (value >> 16) as u8,
+        (value >> 24) as u8,
+    ])
+}
+
+fn write_f32<W>(writer: &mut W, value: f32) -> io::Result<()>
+where
+    W: Write,
+{
+    write_u32(writer, value.to_bits())
+}
@@ -0,0 +1,131 @@
+//! Wavefront OBJ export.
+//!
+//! Each material is written as a group that uses a material named after its
+//! `ResourceId`. Materials are defined in a separate material library (MTL)
+//! file, which is referenced by the OBJ file and written with
+//! `write_materials`. Vertex colors are written after positions, which is a
+//! widely supported extension of the format.
+
+use failure::Error;
+use plexus::buffer::MeshBuffer;
+use std::collections::HashMap;
+use std::io::Write;
+
+use format;
+use render::{Index, Vertex};
+use resource::ResourceId;
+
+/// Writes meshes grouped by material as a Wavefront OBJ file.
+///
+/// `library` is the path of the material library that defines the materials
+/// used by the groups, relative to the OBJ file. See `write_materials`.
+pub fn write<'a, W, I>(writer: &mut W, library: &str, buffers: I) -> Result<(), Error>
+where
+    W: Write,
+    I: IntoIterator<Item = (&'a ResourceId, &'a MeshBuffer<Index, Vertex>)>,
+{
+    writeln!(writer, "# bismuth")?;
+    writeln!(writer, "mtllib {}", library)?;
+    // OBJ indices are one-based and shared by all groups.
+    let mut offset = 1;
+    for (material, buffer) in buffers {
+        let vertices = buffer.as_vertex_slice();
+        for vertex in vertices {
+            let (position, color) = (vertex.position, vertex.color);
+            writeln!(
+                writer,
+                "v {} {} {} {} {} {}",
+                position[0],
+                position[1],
+                position[2],
+                color[0],
+                color[1],
+                color[2]
+            )?;
+        }
+        for vertex in vertices {
+            writeln!(writer, "vt {} {}", vertex.uv[0], vertex.uv[1])?;
+        }
+        let name = format::material_name(*material);
+        writeln!(writer, "g {}", name)?;
+        writeln!(writer, "usemtl {}", name)?;
+        for indices in buffer.as_index_slice().chunks(3) {
+            if indices.len() == 3 {
+                let (a, b, c) = (
+                    indices[0] as usize + offset,
+                    indices[1] as usize + offset,
+                    indices[2] as usize + offset,
+                );
+                writeln!(writer, "f {}/{} {}/{} {}/{}", a, a, b, b, c, c)?;
+            }
+        }
+        offset += vertices.len();
+    }
+    Ok(())
+}
+
+/// Writes a Wavefront material library (MTL) file that defines the given
+/// materials.
+///
+/// `textures` maps materials to the paths of their textures, relative to the
+/// material library. Materials without a texture are plain white.
+pub fn write_materials<'a, W, I>(
+    writer: &mut W,
+    materials: I,
+    textures: &HashMap<ResourceId, String>,
+) -> Result<(), Error>
+where
+    W: Write,
+    I: IntoIterator<Item = &'a ResourceId>,
+{
+    writeln!(writer, "# bismuth")?;
+    for material in materials {
+        writeln!(writer, "newmtl {}", format::material_name(*material))?;
+        writeln!(writer, "Kd 1 1 1")?;
+        writeln!(writer, "illum 1")?;
+        if let Some(texture) = textures.get(material) {
+            writeln!(writer, "map_Kd {}", texture)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cube::{LogWidth, Tree};
+    use render::ToMeshBuffer;
+
+    use super::*;
+
+    #[test]
+    fn write_groups_and_materials() {
+        let tree = Tree::new(LogWidth::new(4));
+        let buffers = tree.as_cube().to_mesh_buffers();
+        let mut obj = vec![];
+        write(&mut obj, "world.mtl", &buffers).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+
+        let count = |prefix: &str| obj.lines().filter(|line| line.starts_with(prefix)).count();
+        let vertices = buffers[&0].as_vertex_slice().len();
+        assert!(obj.lines().any(|line| line == "mtllib world.mtl"));
+        assert!(obj.lines().any(|line| line == "usemtl material0"));
+        assert_eq!(vertices, count("v "));
+        assert_eq!(vertices, count("vt "));
+        assert_eq!(12, count("f "));
+        // Indices are one-based and refer to written vertices.
+        for line in obj.lines().filter(|line| line.starts_with("f ")) {
+            for index in line[2..].split(' ').map(|pair| pair.split('/').next().unwrap()) {
+                let index = index.parse::<usize>().unwrap();
+                assert!(index >= 1 && index <= vertices);
+            }
+        }
+
+        let mut textures = HashMap::new();
+        textures.insert(0, "stone.png".to_owned());
+        let mut mtl = vec![];
+        write_materials(&mut mtl, buffers.keys(), &textures).unwrap();
+        let mtl = String::from_utf8(mtl).unwrap();
+        assert!(mtl.lines().any(|line| line == "newmtl material0"));
+        assert!(mtl.lines().any(|line| line == "map_Kd stone.png"));
+    }
+}
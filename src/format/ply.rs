@@ -0,0 +1,115 @@
+//! Stanford PLY export.
+//!
+//! Meshes are written in the ASCII variant of the format. Vertices include
+//! texture coordinates and colors, and faces include the `ResourceId` of
+//! their material.
+
+use failure::Error;
+use plexus::buffer::MeshBuffer;
+use std::io::Write;
+
+use render::{Index, Vertex};
+use resource::ResourceId;
+
+/// Writes meshes grouped by material as a PLY file.
+pub fn write<'a, W, I>(writer: &mut W, buffers: I) -> Result<(), Error>
+where
+    W: Write,
+    I: IntoIterator<Item = (&'a ResourceId, &'a MeshBuffer<Index, Vertex>)>,
+{
+    let buffers = buffers.into_iter().collect::<Vec<_>>();
+    let vertices = buffers
+        .iter()
+        .map(|&(_, buffer)| buffer.as_vertex_slice().len())
+        .sum::<usize>();
+    let faces = buffers
+        .iter()
+        .map(|&(_, buffer)| buffer.as_index_slice().len() / 3)
+        .sum::<usize>();
+
+    writeln!(writer, "ply")?;
+    writeln!(writer, "format ascii 1.0")?;
+    writeln!(writer, "comment bismuth")?;
+    writeln!(writer, "element vertex {}", vertices)?;
+    for property in &["x", "y", "z", "s", "t"] {
+        writeln!(writer, "property float {}", property)?;
+    }
+    for property in &["red", "green", "blue", "alpha"] {
+        writeln!(writer, "property uchar {}", property)?;
+    }
+    writeln!(writer, "element face {}", faces)?;
+    writeln!(writer, "property list uchar uint vertex_indices")?;
+    writeln!(writer, "property ushort material")?;
+    writeln!(writer, "end_header")?;
+
+    for &(_, buffer) in &buffers {
+        for vertex in buffer.as_vertex_slice() {
+            let (position, uv, color) = (vertex.position, vertex.uv, vertex.color);
+            writeln!(
+                writer,
+                "{} {} {} {} {} {} {} {} {}",
+                position[0],
+                position[1],
+                position[2],
+                uv[0],
+                uv[1],
+                channel(color[0]),
+                channel(color[1]),
+                channel(color[2]),
+                channel(color[3])
+            )?;
+        }
+    }
+    let mut offset = 0;
+    for &(material, buffer) in &buffers {
+        for indices in buffer.as_index_slice().chunks(3) {
+            if indices.len() == 3 {
+                writeln!(
+                    writer,
+                    "3 {} {} {} {}",
+                    indices[0] as usize + offset,
+                    indices[1] as usize + offset,
+                    indices[2] as usize + offset,
+                    material
+                )?;
+            }
+        }
+        offset += buffer.as_vertex_slice().len();
+    }
+    Ok(())
+}
+
+fn channel(value: f32) -> u8 {
+    (value.max(0.0).min(1.0) * 255.0).round() as u8
+}
+
+#[cfg(test)]
+mod tests {
+    use cube::{LogWidth, Tree};
+    use render::ToMeshBuffer;
+
+    use super::*;
+
+    #[test]
+    fn write_header_and_elements() {
+        let tree = Tree::new(LogWidth::new(4));
+        let buffers = tree.as_cube().to_mesh_buffers();
+        let mut data = vec![];
+        write(&mut data, &buffers).unwrap();
+        let data = String::from_utf8(data).unwrap();
+
+        let vertices = buffers[&0].as_vertex_slice().len();
+        let lines = data.lines().collect::<Vec<_>>();
+        let header = lines.iter().position(|line| *line == "end_header").unwrap();
+        assert!(lines.contains(&format!("element vertex {}", vertices).as_str()));
+        assert!(lines.contains(&"element face 12"));
+        assert_eq!(vertices + 12, lines.len() - header - 1);
+        // Faces are triangles tagged with their material.
+        for face in &lines[(header + 1 + vertices)..] {
+            let values = face.split(' ').collect::<Vec<_>>();
+            assert_eq!(5, values.len());
+            assert_eq!("3", values[0]);
+            assert_eq!("0", values[4]);
+        }
+    }
+}
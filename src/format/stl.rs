@@ -0,0 +1,83 @@
+//! Binary STL export.
+//!
+//! STL only describes triangles, so texture coordinates and colors are not
+//! written. The attribute of each triangle is set to the `ResourceId` of its
+//! material. Because the format has no units, positions are written as-is.
+
+use failure::Error;
+use plexus::buffer::MeshBuffer;
+use std::io::Write;
+
+use format;
+use render::{Index, Vertex};
+use resource::ResourceId;
+
+/// Writes meshes grouped by material as a binary STL file.
+pub fn write<'a, W, I>(writer: &mut W, buffers: I) -> Result<(), Error>
+where
+    W: Write,
+    I: IntoIterator<Item = (&'a ResourceId, &'a MeshBuffer<Index, Vertex>)>,
+{
+    let triangles = buffers
+        .into_iter()
+        .flat_map(|(material, buffer)| {
+            format::triangles(buffer)
+                .into_iter()
+                .map(move |triangle| (*material, triangle))
+        })
+        .collect::<Vec<_>>();
+
+    let mut header = [0u8; 80];
+    header[..7].copy_from_slice(b"bismuth");
+    writer.write_all(&header)?;
+    format::write_u32(writer, triangles.len() as u32)?;
+    for (material, triangle) in triangles {
+        let normal = format::normal(&triangle);
+        for value in &[normal.x, normal.y, normal.z] {
+            format::write_f32(writer, *value)?;
+        }
+        for vertex in &triangle {
+            for value in &vertex.position {
+                format::write_f32(writer, *value)?;
+            }
+        }
+        format::write_u16(writer, material)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use cube::{LogWidth, Tree};
+    use render::ToMeshBuffer;
+
+    use super::*;
+
+    #[test]
+    fn write_triangles() {
+        let tree = Tree::new(LogWidth::new(4));
+        let buffers = tree.as_cube().to_mesh_buffers();
+        let mut data = vec![];
+        write(&mut data, &buffers).unwrap();
+
+        // An 80 byte header and count are followed by 50 bytes per triangle.
+        assert_eq!(80 + 4 + (12 * 50), data.len());
+        assert_eq!(&b"bismuth"[..], &data[..7]);
+        assert_eq!([12, 0, 0, 0], [data[80], data[81], data[82], data[83]]);
+        // Each normal of a cube is a unit vector along an axis.
+        for triangle in data[84..].chunks(50) {
+            let normal = (0..3)
+                .map(|index| {
+                    let bytes = &triangle[(index * 4)..((index + 1) * 4)];
+                    let bits = bytes
+                        .iter()
+                        .rev()
+                        .fold(0u32, |bits, byte| (bits << 8) | *byte as u32);
+                    f32::from_bits(bits)
+                })
+                .collect::<Vec<_>>();
+            let norm = normal.iter().map(|x| x * x).sum::<f32>();
+            assert!((norm - 1.0).abs() < 1.0e-5);
+        }
+    }
+}
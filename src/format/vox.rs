@@ -0,0 +1,177 @@
+//! MagicaVoxel `.vox` import and export.
+//!
+//! MagicaVoxel uses a Z-up coordinate system, so models are rotated about
+//! the X axis such that their Z axis is aligned with the Y axis of the tree.
+//! Voxels are indexed into a palette; palette indices map directly to
+//! `ResourceId`s. Only the first model in a file is read.
+
+use failure::Error;
+use std::cmp;
+use std::io::{Read, Write};
+
+use cube::{Cube, Geometry, LogWidth, Node, Spatial, Tree};
+use format::{self, FormatError};
+use math::{UPoint3, UScalar, UVector3};
+use resource::ResourceId;
+use BoolExt;
+
+const VERSION: u32 = 150;
+
+/// The maximum extent of a model along any axis.
+const MAX_EXTENT: UScalar = 256;
+
+/// Reads a `.vox` model into a new `Tree`.
+///
+/// Each voxel is placed as a full leaf with the minimum width and its
+/// palette index as its material. The width of the `Tree` is the smallest
+/// width that contains the model.
+pub fn read<R>(reader: &mut R) -> Result<Tree, Error>
+where
+    R: Read,
+{
+    let mut data = vec![];
+    reader.read_to_end(&mut data)?;
+    if data.len() < 20 || &data[..4] != b"VOX " || &data[8..12] != b"MAIN" {
+        return Err(FormatError::UnrecognizedFormat.into());
+    }
+    let mut size = None;
+    let mut voxels = None;
+    // Skip the content of the main chunk and read its children.
+    let mut chunks = data.get((20 + le_u32(&data[12..]) as usize)..)
+        .ok_or(FormatError::Malformed)?;
+    while chunks.len() >= 12 {
+        let content = le_u32(&chunks[4..]) as usize;
+        let end = 12 + content + le_u32(&chunks[8..]) as usize;
+        if end > chunks.len() {
+            return Err(FormatError::Malformed.into());
+        }
+        let (id, body) = (&chunks[..4], &chunks[12..(12 + content)]);
+        if id == b"SIZE" && size.is_none() && body.len() >= 12 {
+            size = Some(UVector3::new(le_u32(body), le_u32(&body[4..]), le_u32(&body[8..])));
+        }
+        else if id == b"XYZI" && voxels.is_none() && body.len() >= 4 {
+            voxels = body.get(4..(4 + (le_u32(body) as usize * 4)));
+        }
+        chunks = &chunks[end..];
+    }
+    let (size, voxels) = match (size, voxels) {
+        (Some(size), Some(voxels)) => (size, voxels),
+        _ => return Err(FormatError::Malformed.into()),
+    };
+
+    let unit = LogWidth::min_value();
+    let extent = cmp::max(cmp::max(size.x, size.y), size.z);
+    if extent > MAX_EXTENT {
+        return Err(FormatError::TooLarge.into());
+    }
+    let root = (32 - (cmp::max(extent, 1) - 1).leading_zeros()) as u8;
+    let mut tree = Tree::empty(LogWidth::new(unit.to_inner() + root));
+    for voxel in voxels.chunks(4) {
+        let (x, y, z) = (voxel[0] as UScalar, voxel[1] as UScalar, voxel[2] as UScalar);
+        if x >= size.x || y >= size.y || z >= size.z {
+            continue;
+        }
+        let point = UPoint3::new(x, z, size.y - 1 - y) * unit.exp();
+        if let Some(mut cube) = tree.as_cube_mut().subdivide_to_point(&point, unit) {
+            if let Some(leaf) = cube.as_leaf_mut() {
+                leaf.geometry = Geometry::full();
+                leaf.material = voxel[3] as ResourceId;
+            }
+        }
+    }
+    Ok(tree)
+}
+
+/// Writes a cube as a `.vox` model with voxels of the given width.
+///
+/// A voxel is written for each cell of the given width whose center lies in
+/// a non-empty leaf. Materials are used as palette indices and are clamped
+/// to the range `[1, 255]`. No palette is written, so MagicaVoxel uses its
+/// default palette.
+pub fn write<W>(writer: &mut W, cube: &Cube<&Node>, width: LogWidth) -> Result<(), Error>
+where
+    W: Write,
+{
+    let width = cmp::min(width, cube.partition().width());
+    let extent = cube.partition().width().exp() >> width.to_inner();
+    if extent > MAX_EXTENT {
+        return Err(FormatError::TooLarge.into());
+    }
+    let origin = *cube.partition().origin();
+    let mut voxels = vec![];
+    for x in 0..extent {
+        for y in 0..extent {
+            for z in 0..extent {
+                let center = origin + (UVector3::new(x, y, z) * width.exp())
+                    + (UVector3::new(1, 1, 1) * (width.exp() >> 1));
+                let material = cube.at_point(&center, LogWidth::min_value())
+                    .and_then(|cube| {
+                        cube.as_leaf()
+                            .and_then(|leaf| (!leaf.geometry.is_empty()).into_some(leaf.material))
+                    });
+                if let Some(material) = material {
+                    let index = cmp::min(cmp::max(material, 1), 255) as u8;
+                    voxels.push([x as u8, (extent - 1 - z) as u8, y as u8, index]);
+                }
+            }
+        }
+    }
+
+    let content = 4 + (4 * voxels.len() as u32);
+    writer.write_all(b"VOX ")?;
+    format::write_u32(writer, VERSION)?;
+    writer.write_all(b"MAIN")?;
+    format::write_u32(writer, 0)?;
+    format::write_u32(writer, (12 + 12) + (12 + content))?;
+    writer.write_all(b"SIZE")?;
+    format::write_u32(writer, 12)?;
+    format::write_u32(writer, 0)?;
+    for _ in 0..3 {
+        format::write_u32(writer, extent)?;
+    }
+    writer.write_all(b"XYZI")?;
+    format::write_u32(writer, content)?;
+    format::write_u32(writer, 0)?;
+    format::write_u32(writer, voxels.len() as u32)?;
+    for voxel in voxels {
+        writer.write_all(&voxel)?;
+    }
+    Ok(())
+}
+
+fn le_u32(bytes: &[u8]) -> u32 {
+    (bytes[0] as u32) | ((bytes[1] as u32) << 8) | ((bytes[2] as u32) << 16)
+        | ((bytes[3] as u32) << 24)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn round_trip() {
+        let mut tree = Tree::empty(LogWidth::new(6));
+        {
+            let point = UPoint3::new(16, 0, 32);
+            let mut cube = tree.as_cube_mut()
+                .subdivide_to_point(&point, LogWidth::min_value())
+                .unwrap();
+            let leaf = cube.as_leaf_mut().unwrap();
+            leaf.geometry = Geometry::full();
+            leaf.material = 7;
+        }
+        let mut data = vec![];
+        write(&mut data, &tree.as_cube(), LogWidth::min_value()).unwrap();
+        let tree = read(&mut data.as_slice()).unwrap();
+
+        let mut voxels = vec![];
+        for cube in tree.as_cube().iter() {
+            if let Some(leaf) = cube.as_leaf() {
+                if !leaf.geometry.is_empty() {
+                    voxels.push((*cube.partition().origin(), leaf.material));
+                }
+            }
+        }
+        assert_eq!(vec![(UPoint3::new(16, 0, 32), 7)], voxels);
+    }
+}
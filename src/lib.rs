@@ -22,11 +22,14 @@ extern crate winit;
 pub mod clamp;
 pub mod cube;
 pub mod event;
+pub mod format;
 pub mod framework;
 pub mod input;
 pub mod math;
 pub mod render;
 pub mod resource;
+pub mod sdf;
+pub mod terrain;
 
 pub trait BoolExt: Sized {
     fn into_some<T>(self, some: T) -> Option<T>;
@@ -1,17 +1,16 @@
 extern crate bismuth;
 extern crate glutin;
 extern crate nalgebra;
-extern crate plexus;
 
-use bismuth::cube::{Cursor, Geometry, LogWidth, Spatial, Tree};
+use bismuth::cube::{Cursor, Edit, LogWidth, MeshCache, MeshOptions, Spatial, Tree};
 use bismuth::event::{ElementState, Event, MouseButton, React};
 use bismuth::framework::{self, Activity, Context, Harness, RenderContextView, RenderResult,
                          Transition, UpdateContextView, UpdateResult, WindowView};
 use bismuth::input::{InputState, InputTransition, Mouse, MousePosition, Snapshot};
 use bismuth::math::{FMatrix4, FPoint3, FScalar, IntoSpace, UPoint3, UVector3};
-use bismuth::render::{Camera, Index, MetaRenderer, Projection, ToMeshBuffer, Transform, Vertex};
+use bismuth::render::{AtlasBuilder, Camera, MetaRenderer, Projection, Transform};
+use bismuth::resource::Registry;
 use glutin::WindowBuilder;
-use plexus::buffer::MeshBuffer;
 use std::marker::PhantomData;
 
 struct State {
@@ -39,7 +38,7 @@ where
     R: MetaRenderer,
 {
     tree: Tree,
-    mesh: MeshBuffer<Index, Vertex>,
+    meshes: MeshCache,
     camera: Camera,
     phantom: PhantomData<R>,
 }
@@ -49,12 +48,25 @@ where
     R: MetaRenderer,
 {
     pub fn new(context: &mut Context<State, R>) -> Self {
-        let tree = new_tree(LogWidth::new(8));
-        let mesh = tree.as_cube().to_mesh_buffer();
+        let width = LogWidth::new(8);
+        let registry = Registry::from_file("data/materials.ini").unwrap();
+        registry.validate().unwrap();
+        let atlas = AtlasBuilder::from_registry(&registry).unwrap().build();
+        context.renderer.set_atlas(&atlas).unwrap();
+        let mut tree = new_tree(width);
+        let meshes = MeshCache::new(
+            &mut tree,
+            width - 2,
+            MeshOptions {
+                mappings: registry.mappings(),
+                atlas: atlas.rects().clone(),
+                ..MeshOptions::default()
+            },
+        );
         let camera = new_camera(&context.renderer.window, &tree);
         MainActivity {
             tree: tree,
-            mesh: mesh,
+            meshes: meshes,
             camera: camera,
             phantom: PhantomData,
         }
@@ -66,23 +78,20 @@ where
     R: MetaRenderer,
 {
     fn update(&mut self, context: &mut UpdateContextView<State = State>) -> UpdateResult<State, R> {
-        let mut dirty = false;
         if let Some(ElementState::Pressed) = context.state().mouse.transition(MouseButton::Left) {
             let ray = self.camera.cast_ray(
                 context.window(),
                 &context.state().mouse.state(MousePosition),
             );
-            let mut cube = self.tree.as_cube_mut();
-            if let Some((_, mut cube)) = cube.at_ray_mut(&ray, LogWidth::min_value()) {
-                if let Some(leaf) = cube.as_leaf_mut() {
-                    leaf.geometry = Geometry::empty();
-                    dirty = true;
-                }
+            let cursor = self.tree
+                .as_cube()
+                .at_ray(&ray, LogWidth::min_value())
+                .map(|(_, cube)| Cursor::at_cube(&cube));
+            if let Some(cursor) = cursor {
+                self.tree.as_cube_mut().apply(&cursor, &Edit::Clear);
             }
         }
-        if dirty {
-            self.mesh = self.tree.as_cube().to_mesh_buffer();
-        }
+        self.meshes.update(&mut self.tree);
         context.state_mut().mouse.snapshot();
         Ok(Transition::None)
     }
@@ -96,7 +105,9 @@ where
                 &FMatrix4::identity(),
             ))
             .unwrap();
-        renderer.draw_mesh_buffer(&self.mesh);
+        for buffer in self.meshes.mesh_buffers() {
+            renderer.draw_mesh_buffer(buffer);
+        }
         renderer.flush().unwrap();
         Ok(())
     }
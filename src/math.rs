@@ -1,5 +1,5 @@
 use alga::general::{Real, SupersetOf};
-use nalgebra::{self, Matrix4, Point2, Point3, Scalar, Unit, Vector3, Vector4};
+use nalgebra::{self, Matrix4, Point2, Point3, Scalar, Unit, Vector2, Vector3, Vector4};
 use num::{Float, Integer};
 use std::cmp;
 use std::ops;
@@ -13,6 +13,7 @@ pub type UVector3 = Vector3<UScalar>;
 
 pub type FPoint2 = Point2<FScalar>;
 pub type FPoint3 = Point3<FScalar>;
+pub type FVector2 = Vector2<FScalar>;
 pub type FVector3 = Vector3<FScalar>;
 pub type FVector4 = Vector4<FScalar>;
 pub type FMatrix4 = Matrix4<FScalar>;
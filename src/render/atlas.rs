@@ -0,0 +1,247 @@
+use failure::Error;
+use image::{self, Rgba, RgbaImage};
+use std::cmp;
+use std::collections::HashMap;
+
+use math::{FPoint2, FScalar, FVector2};
+use resource::{Registry, ResourceId};
+
+/// The width in pixels of the border surrounding each image in an `Atlas`.
+///
+/// Borders repeat the edge pixels of their image, so that filtering near the
+/// edge of a rectangle does not sample neighboring images.
+pub const GUTTER: u32 = 4;
+
+/// A sub-rectangle of an `Atlas` in normalized texture coordinates.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasRect {
+    pub origin: FPoint2,
+    pub size: FVector2,
+}
+
+impl AtlasRect {
+    pub fn new(origin: FPoint2, size: FVector2) -> Self {
+        AtlasRect {
+            origin: origin,
+            size: size,
+        }
+    }
+}
+
+impl Default for AtlasRect {
+    /// Gets a rectangle spanning the entire texture.
+    fn default() -> Self {
+        AtlasRect::new(FPoint2::origin(), FVector2::new(1.0, 1.0))
+    }
+}
+
+/// Packs per-material images into an `Atlas`.
+#[derive(Default)]
+pub struct AtlasBuilder {
+    images: Vec<(ResourceId, RgbaImage)>,
+}
+
+impl AtlasBuilder {
+    pub fn new() -> Self {
+        AtlasBuilder::default()
+    }
+
+    /// Creates a builder with the texture of each material in a `Registry`.
+    ///
+    /// Textures are multiplied by the tint of their material. Materials
+    /// without a texture use a single pixel of their tint.
+    pub fn from_registry(registry: &Registry) -> Result<Self, Error> {
+        let mut builder = AtlasBuilder::new();
+        for material in registry.iter() {
+            let mut image = match material.texture {
+                Some(ref path) => image::open(path)?.to_rgba(),
+                _ => RgbaImage::from_pixel(1, 1, Rgba([u8::max_value(); 4])),
+            };
+            for pixel in image.pixels_mut() {
+                for (channel, tint) in pixel.data.iter_mut().zip(material.tint.iter()) {
+                    *channel = (*channel as FScalar * tint.max(0.0).min(1.0)).round() as u8;
+                }
+            }
+            builder.insert(material.id, image);
+        }
+        Ok(builder)
+    }
+
+    /// Inserts the image of a material, replacing any previous image.
+    pub fn insert(&mut self, material: ResourceId, image: RgbaImage) {
+        self.images.retain(|&(id, _)| id != material);
+        self.images.push((material, image));
+    }
+
+    /// Packs the images into an `Atlas`.
+    ///
+    /// Images are sorted by height and placed left to right in shelves. Each
+    /// image is surrounded by a border of `GUTTER` pixels that repeats its
+    /// edges. The dimensions of the atlas are powers of two.
+    pub fn build(mut self) -> Atlas {
+        self.images
+            .sort_by(|a, b| b.1.height().cmp(&a.1.height()).then(a.0.cmp(&b.0)));
+        let padded = |image: &RgbaImage| {
+            (image.width() + (GUTTER * 2), image.height() + (GUTTER * 2))
+        };
+        let area = self.images
+            .iter()
+            .map(|&(_, ref image)| {
+                let (width, height) = padded(image);
+                width * height
+            })
+            .sum::<u32>();
+        let widest = self.images
+            .iter()
+            .map(|&(_, ref image)| padded(image).0)
+            .max()
+            .unwrap_or(1);
+        let width = cmp::max(widest, (area as f64).sqrt().ceil() as u32).next_power_of_two();
+        let mut placements = Vec::with_capacity(self.images.len());
+        let (mut x, mut y, mut shelf) = (0, 0, 0);
+        for &(_, ref image) in &self.images {
+            let (image_width, image_height) = padded(image);
+            if x + image_width > width {
+                x = 0;
+                y += shelf;
+                shelf = 0;
+            }
+            placements.push((x + GUTTER, y + GUTTER));
+            x += image_width;
+            shelf = cmp::max(shelf, image_height);
+        }
+        let height = cmp::max(y + shelf, 1).next_power_of_two();
+        let mut atlas = RgbaImage::new(width, height);
+        let mut rects = HashMap::with_capacity(self.images.len());
+        for (&(material, ref image), &(x, y)) in self.images.iter().zip(placements.iter()) {
+            let (image_width, image_height) = padded(image);
+            for v in 0..image_height {
+                for u in 0..image_width {
+                    // Clamp to the nearest pixel of the image, which extends
+                    // its edges into the gutter.
+                    let pixel = image.get_pixel(
+                        cmp::min(u.saturating_sub(GUTTER), image.width() - 1),
+                        cmp::min(v.saturating_sub(GUTTER), image.height() - 1),
+                    );
+                    atlas.put_pixel(x - GUTTER + u, y - GUTTER + v, *pixel);
+                }
+            }
+            rects.insert(
+                material,
+                AtlasRect::new(
+                    FPoint2::new(
+                        x as FScalar / width as FScalar,
+                        y as FScalar / height as FScalar,
+                    ),
+                    FVector2::new(
+                        image.width() as FScalar / width as FScalar,
+                        image.height() as FScalar / height as FScalar,
+                    ),
+                ),
+            );
+        }
+        Atlas {
+            image: atlas,
+            rects: rects,
+        }
+    }
+}
+
+/// A texture containing the images of many materials.
+///
+/// Each material is mapped to an `AtlasRect` within the texture. Texture
+/// coordinates are wrapped within the rectangle of their material when
+/// rendering, so textures continue to tile. Rectangles are surrounded by a
+/// gutter of `GUTTER` pixels, so filtering does not bleed between materials.
+pub struct Atlas {
+    image: RgbaImage,
+    rects: HashMap<ResourceId, AtlasRect>,
+}
+
+impl Atlas {
+    pub fn image(&self) -> &RgbaImage {
+        &self.image
+    }
+
+    pub fn rect(&self, material: ResourceId) -> Option<&AtlasRect> {
+        self.rects.get(&material)
+    }
+
+    /// Gets the `AtlasRect` of each material, such as for use with
+    /// `MeshOptions`.
+    pub fn rects(&self) -> &HashMap<ResourceId, AtlasRect> {
+        &self.rects
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pack_without_overlap() {
+        let mut builder = AtlasBuilder::new();
+        builder.insert(0, RgbaImage::from_pixel(16, 16, Rgba([255, 0, 0, 255])));
+        builder.insert(1, RgbaImage::from_pixel(8, 8, Rgba([0, 255, 0, 255])));
+        builder.insert(2, RgbaImage::from_pixel(8, 4, Rgba([0, 0, 255, 255])));
+        let atlas = builder.build();
+
+        let (width, height) = atlas.image().dimensions();
+        assert!(width.is_power_of_two() && height.is_power_of_two());
+        let rects = (0..3)
+            .map(|material| *atlas.rect(material).unwrap())
+            .collect::<Vec<_>>();
+        for (index, a) in rects.iter().enumerate() {
+            for b in &rects[(index + 1)..] {
+                let disjoint = a.origin.x + a.size.x <= b.origin.x
+                    || b.origin.x + b.size.x <= a.origin.x
+                    || a.origin.y + a.size.y <= b.origin.y
+                    || b.origin.y + b.size.y <= a.origin.y;
+                assert!(disjoint);
+            }
+        }
+        // Pixels are copied into the rectangle of their material.
+        let rect = &rects[1];
+        let x = (rect.origin.x * width as FScalar) as u32;
+        let y = (rect.origin.y * height as FScalar) as u32;
+        assert_eq!(Rgba([0, 255, 0, 255]), *atlas.image().get_pixel(x, y));
+    }
+
+    #[test]
+    fn pad_with_edge_pixels() {
+        let mut image = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 255]));
+        image.put_pixel(0, 0, Rgba([0, 0, 255, 255]));
+        let mut builder = AtlasBuilder::new();
+        builder.insert(0, image);
+        builder.insert(1, RgbaImage::from_pixel(4, 4, Rgba([0, 255, 0, 255])));
+        let atlas = builder.build();
+
+        let (width, height) = atlas.image().dimensions();
+        let pixels = |rect: &AtlasRect| {
+            (
+                (rect.origin.x * width as FScalar).round() as u32,
+                (rect.origin.y * height as FScalar).round() as u32,
+                (rect.size.x * width as FScalar).round() as u32,
+                (rect.size.y * height as FScalar).round() as u32,
+            )
+        };
+        let a = pixels(atlas.rect(0).unwrap());
+        let b = pixels(atlas.rect(1).unwrap());
+        assert_eq!((4, 4), (a.2, a.3));
+        // Rectangles are separated by the gutters of both images.
+        let gap_x = cmp::max(a.0, b.0) as i64 - cmp::min(a.0 + a.2, b.0 + b.2) as i64;
+        let gap_y = cmp::max(a.1, b.1) as i64 - cmp::min(a.1 + a.3, b.1 + b.3) as i64;
+        assert!(cmp::max(gap_x, gap_y) >= (GUTTER * 2) as i64);
+        assert!(a.0 >= GUTTER && a.1 >= GUTTER);
+        // Gutters repeat the nearest edge pixel of their image.
+        let (x, y) = (a.0, a.1);
+        for offset in 1..(GUTTER + 1) {
+            let corner = Rgba([0, 0, 255, 255]);
+            assert_eq!(corner, *atlas.image().get_pixel(x - offset, y - offset));
+            assert_eq!(corner, *atlas.image().get_pixel(x - offset, y));
+            let edge = Rgba([255, 0, 0, 255]);
+            assert_eq!(edge, *atlas.image().get_pixel(x + a.2 - 1 + offset, y + 1));
+            assert_eq!(edge, *atlas.image().get_pixel(x + 1, y + a.3 - 1 + offset));
+        }
+    }
+}
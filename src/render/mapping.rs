@@ -0,0 +1,112 @@
+use math::{FPoint2, FScalar, FVector2};
+
+/// The smallest scale of a `TextureMapping`. Smaller scales, including zero
+/// and negative scales, are clamped to this value.
+pub const MIN_SCALE: FScalar = 1.0e-3;
+
+/// Determines how texture coordinates are projected onto faces.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum TextureProjection {
+    /// Projects positions onto the plane of the nominal face of a cube, so
+    /// textures are aligned to the world and tile across adjacent faces.
+    World,
+    /// Stretches the texture across each face, such that texture coordinates
+    /// span from zero to one regardless of the size of the face.
+    Stretch,
+    /// Projects positions onto the axis-aligned plane that most closely
+    /// matches the actual normal of a face. This avoids stretching textures
+    /// across sloped faces of deformed cubes.
+    Triplanar,
+}
+
+/// Texture mapping settings for a material.
+#[derive(Clone, Copy, Debug)]
+pub struct TextureMapping {
+    pub projection: TextureProjection,
+    /// Rotation of the texture about the origin of texture space in radians.
+    /// Because textures repeat, quarter turns keep stretched textures within
+    /// a single tile.
+    pub rotation: FScalar,
+    /// Scale of the texture. Larger values repeat the texture less often.
+    /// Scales are clamped to `MIN_SCALE`.
+    pub scale: FScalar,
+}
+
+impl TextureMapping {
+    pub fn new(projection: TextureProjection) -> Self {
+        TextureMapping {
+            projection: projection,
+            ..TextureMapping::default()
+        }
+    }
+
+    pub fn with_rotation(mut self, rotation: FScalar) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    /// Sets the scale of the mapping, clamping it to `MIN_SCALE`.
+    pub fn with_scale(mut self, scale: FScalar) -> Self {
+        self.scale = clamp_scale(scale);
+        self
+    }
+
+    /// Applies the rotation and scale of the mapping to projected texture
+    /// coordinates.
+    pub fn transform(&self, uv: &FPoint2) -> FPoint2 {
+        let (sin, cos) = self.rotation.sin_cos();
+        let uv = uv.coords / clamp_scale(self.scale);
+        FPoint2::origin() + FVector2::new((uv.x * cos) - (uv.y * sin), (uv.x * sin) + (uv.y * cos))
+    }
+}
+
+impl Default for TextureMapping {
+    fn default() -> Self {
+        TextureMapping {
+            projection: TextureProjection::World,
+            rotation: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+/// Clamps a scale to `MIN_SCALE`. `NaN`s are also clamped.
+fn clamp_scale(scale: FScalar) -> FScalar {
+    if scale >= MIN_SCALE {
+        scale
+    }
+    else {
+        MIN_SCALE
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn transform_rotates_and_scales() {
+        let mapping = TextureMapping::default()
+            .with_rotation(::std::f32::consts::PI as FScalar)
+            .with_scale(2.0);
+        let uv = mapping.transform(&FPoint2::new(1.0, 0.5));
+
+        // A half turn reflects the point through the origin, and doubling the
+        // scale halves its distance from the origin.
+        assert!((uv.x + 0.5).abs() < 1.0e-5);
+        assert!((uv.y + 0.25).abs() < 1.0e-5);
+    }
+
+    #[test]
+    fn non_positive_scales_are_clamped() {
+        let uv = FPoint2::new(1.0, 1.0);
+        for scale in &[0.0, -1.0, ::std::f32::NAN as FScalar] {
+            let mapping = TextureMapping::default().with_scale(*scale);
+            assert_eq!(MIN_SCALE, mapping.scale);
+            let mut mapping = TextureMapping::default();
+            mapping.scale = *scale;
+            let uv = mapping.transform(&uv);
+            assert!(uv.x.is_finite() && uv.y.is_finite());
+        }
+    }
+}
@@ -1,21 +1,28 @@
 use plexus::buffer::MeshBuffer;
 use rand;
+use std::collections::BTreeMap;
 use std::ops::{Deref, DerefMut};
 
 use math::{FScalar, FVector4};
+use resource::ResourceId;
 
+mod atlas;
 mod camera;
+mod mapping;
 pub mod pipeline;
 mod renderer;
 mod texture;
 
+pub use self::atlas::{Atlas, AtlasBuilder, AtlasRect};
 pub use self::camera::{AspectRatio, Camera, Projection};
+pub use self::mapping::{TextureMapping, TextureProjection, MIN_SCALE};
 pub use self::pipeline::{Transform, Vertex};
 pub use self::renderer::{GlutinRenderer, MetaRenderer, Renderer};
 pub use self::texture::Texture;
 
 pub type Index = u32;
 
+#[derive(Clone, Copy, Debug)]
 pub struct Color(FVector4);
 
 impl Color {
@@ -57,4 +64,15 @@ impl DerefMut for Color {
 
 pub trait ToMeshBuffer {
     fn to_mesh_buffer(&self) -> MeshBuffer<Index, Vertex>;
+
+    /// Generates a `MeshBuffer` for each material, so that material state can
+    /// be bound per batch when rendering or exporting.
+    ///
+    /// Buffers are keyed and ordered by material. By default, the entire
+    /// mesh is assigned to the default material.
+    fn to_mesh_buffers(&self) -> BTreeMap<ResourceId, MeshBuffer<Index, Vertex>> {
+        let mut buffers = BTreeMap::new();
+        buffers.insert(ResourceId::default(), self.to_mesh_buffer());
+        buffers
+    }
 }
@@ -7,7 +7,7 @@ use gfx;
 use std::hash::{Hash, Hasher};
 
 use math::{FMatrix4, FPoint2, FPoint3, Matrix4Ext};
-use render::Color;
+use render::{AtlasRect, Color};
 
 pub use self::pipeline::*;
 
@@ -52,6 +52,7 @@ gfx_vertex_struct_meta!{
         position: [f32; 3] = "a_position",
         uv: [f32; 2] = "a_uv",
         color: [f32; 4] = "a_color",
+        rect: [f32; 4] = "a_rect",
     }
 }
 
@@ -61,8 +62,15 @@ impl Vertex {
             position: [position.x, position.y, position.z],
             uv: [uv.x, uv.y],
             color: [color.x, color.y, color.z, color.w],
+            rect: [0.0, 0.0, 1.0, 1.0],
         }
     }
+
+    /// Sets the `AtlasRect` within which texture coordinates are wrapped.
+    pub fn with_rect(mut self, rect: &AtlasRect) -> Self {
+        self.rect = [rect.origin.x, rect.origin.y, rect.size.x, rect.size.y];
+        self
+    }
 }
 
 impl Default for Vertex {
@@ -87,5 +95,6 @@ impl Hash for Vertex {
         decorum::hash_float_array(&self.position, state);
         decorum::hash_float_array(&self.uv, state);
         decorum::hash_float_array(&self.color, state);
+        decorum::hash_float_array(&self.rect, state);
     }
 }
@@ -13,7 +13,7 @@ use framework::WindowView;
 use render::camera::AspectRatio;
 use render::pipeline::{self, Data, Meta, Transform, Vertex};
 use render::texture::Texture;
-use render::Index;
+use render::{Atlas, Index};
 
 const CLEAR_COLOR: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
 
@@ -129,6 +129,13 @@ where
         }
     }
 
+    /// Sets the texture used to draw meshes to an `Atlas`.
+    pub fn set_atlas(&mut self, atlas: &Atlas) -> Result<(), Error> {
+        let texture = Texture::<_, Srgba8>::from_rgba_image(&mut self.factory, atlas.image())?;
+        self.data.sampler = texture.to_pipeline_data();
+        Ok(())
+    }
+
     pub fn set_transform(&mut self, transform: &Transform) -> Result<(), Error> {
         self.data.camera = transform.camera;
         self.data.model = transform.model;
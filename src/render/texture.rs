@@ -3,7 +3,7 @@ use gfx::format::{R8_G8_B8_A8, Rgba8, Srgb, TextureChannel, TextureFormat, Unorm
 use gfx::handle::{Sampler, ShaderResourceView};
 use gfx::texture::{AaMode, FilterMethod, Kind, SamplerInfo, WrapMode};
 use gfx::{self, Factory, Resources};
-use image;
+use image::{self, RgbaImage};
 use std::path::Path;
 
 pub trait NormalizedChannel {}
@@ -66,11 +66,17 @@ where
         F: Factory<R>,
         P: AsRef<Path>,
     {
-        let data = image::open(path)?.to_rgba();
-        let (width, height) = data.dimensions();
+        Self::from_rgba_image(factory, &image::open(path)?.to_rgba())
+    }
+
+    pub fn from_rgba_image<F>(factory: &mut F, image: &RgbaImage) -> Result<Self, Error>
+    where
+        F: Factory<R>,
+    {
+        let (width, height) = image.dimensions();
         let (surface, view) = factory.create_texture_immutable_u8::<(R8_G8_B8_A8, C)>(
             Kind::D2(width as u16, height as u16, AaMode::Single),
-            &[data.into_vec().as_slice()],
+            &[&**image],
         )?;
         Ok(Texture::new(
             surface,
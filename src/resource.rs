@@ -1 +1,330 @@
+//! This module provides the `Registry` of materials referenced by
+//! `ResourceId`s.
+//!
+//! Materials are loaded from a manifest file with a section per material.
+//! Sections are named by the material and contain `key = value` pairs. Lines
+//! beginning with `#` are comments. Texture paths are relative to the
+//! directory of the manifest and rotations are given in degrees.
+//!
+//! ```text
+//! [stone]
+//! id = 0
+//! texture = texture/default.png
+//! tint = 1.0 1.0 1.0 1.0
+//! projection = world
+//! rotation = 0.0
+//! scale = 1.0
+//! density = 2.5
+//! friction = 0.6
+//! restitution = 0.1
+//! ```
+
+use failure::Error;
+use std::collections::{btree_map, BTreeMap, HashMap};
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use math::FScalar;
+use render::{Color, TextureMapping, TextureProjection};
+
 pub type ResourceId = u16;
+
+#[derive(Debug, Fail)]
+pub enum ResourceError {
+    #[fail(display = "malformed manifest at line {}", _0)]
+    Malformed(usize),
+    #[fail(display = "duplicate material: {}", _0)]
+    Duplicate(String),
+    #[fail(display = "material {} is missing an id", _0)]
+    MissingId(String),
+    #[fail(display = "texture not found: {}", _0)]
+    TextureNotFound(String),
+}
+
+/// Physical properties of a material.
+#[derive(Clone, Copy, Debug)]
+pub struct PhysicalProperties {
+    pub density: FScalar,
+    pub friction: FScalar,
+    pub restitution: FScalar,
+}
+
+impl Default for PhysicalProperties {
+    fn default() -> Self {
+        PhysicalProperties {
+            density: 1.0,
+            friction: 0.5,
+            restitution: 0.0,
+        }
+    }
+}
+
+/// A named material.
+#[derive(Clone, Debug)]
+pub struct Material {
+    pub id: ResourceId,
+    pub name: String,
+    /// Path to the texture of the material, if any. Materials without a
+    /// texture are rendered with their tint alone.
+    pub texture: Option<PathBuf>,
+    pub tint: Color,
+    pub mapping: TextureMapping,
+    pub physics: PhysicalProperties,
+}
+
+impl Material {
+    pub fn new(id: ResourceId, name: &str) -> Self {
+        Material {
+            id: id,
+            name: name.to_owned(),
+            texture: None,
+            tint: Color::white(),
+            mapping: TextureMapping::default(),
+            physics: PhysicalProperties::default(),
+        }
+    }
+}
+
+/// Maps `ResourceId`s to `Material`s.
+#[derive(Default)]
+pub struct Registry {
+    materials: BTreeMap<ResourceId, Material>,
+    names: HashMap<String, ResourceId>,
+}
+
+impl Registry {
+    pub fn new() -> Self {
+        Registry::default()
+    }
+
+    /// Loads a registry from a manifest file.
+    ///
+    /// Texture paths are resolved relative to the directory containing the
+    /// manifest. The registry is not validated; see `Registry::validate`.
+    pub fn from_file<P>(path: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let path = path.as_ref();
+        let mut manifest = String::new();
+        File::open(path)?.read_to_string(&mut manifest)?;
+        Registry::from_manifest(&manifest, path.parent().unwrap_or_else(|| Path::new("")))
+    }
+
+    /// Parses a registry from the contents of a manifest.
+    pub fn from_manifest<P>(manifest: &str, base: P) -> Result<Self, Error>
+    where
+        P: AsRef<Path>,
+    {
+        let mut registry = Registry::new();
+        let mut section: Option<(String, Vec<(usize, String, String)>)> = None;
+        for (index, line) in manifest.lines().enumerate() {
+            let number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            if line.starts_with('[') {
+                if !line.ends_with(']') || line.len() < 3 {
+                    return Err(ResourceError::Malformed(number).into());
+                }
+                if let Some((name, entries)) = section.take() {
+                    registry.insert(parse_material(&name, &entries, base.as_ref())?)?;
+                }
+                section = Some((line[1..(line.len() - 1)].trim().to_owned(), vec![]));
+            }
+            else {
+                let mut parts = line.splitn(2, '=');
+                match (section.as_mut(), parts.next(), parts.next()) {
+                    (Some(&mut (_, ref mut entries)), Some(key), Some(value)) => {
+                        entries.push((number, key.trim().to_owned(), value.trim().to_owned()));
+                    }
+                    _ => return Err(ResourceError::Malformed(number).into()),
+                }
+            }
+        }
+        if let Some((name, entries)) = section.take() {
+            registry.insert(parse_material(&name, &entries, base.as_ref())?)?;
+        }
+        Ok(registry)
+    }
+
+    /// Inserts a material into the registry.
+    ///
+    /// Fails if a material with the same id or name has already been
+    /// inserted.
+    pub fn insert(&mut self, material: Material) -> Result<(), ResourceError> {
+        if self.materials.contains_key(&material.id) || self.names.contains_key(&material.name) {
+            return Err(ResourceError::Duplicate(material.name));
+        }
+        self.names.insert(material.name.clone(), material.id);
+        self.materials.insert(material.id, material);
+        Ok(())
+    }
+
+    pub fn get(&self, id: ResourceId) -> Option<&Material> {
+        self.materials.get(&id)
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&Material> {
+        self.names.get(name).and_then(|id| self.materials.get(id))
+    }
+
+    /// Gets the `ResourceId` of the material with the given name.
+    pub fn id(&self, name: &str) -> Option<ResourceId> {
+        self.names.get(name).cloned()
+    }
+
+    /// Iterates over the materials in the registry ordered by id.
+    pub fn iter(&self) -> btree_map::Values<ResourceId, Material> {
+        self.materials.values()
+    }
+
+    pub fn len(&self) -> usize {
+        self.materials.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.materials.is_empty()
+    }
+
+    /// Gets the `TextureMapping` of each material, such as for use with
+    /// `MeshOptions`.
+    pub fn mappings(&self) -> HashMap<ResourceId, TextureMapping> {
+        self.materials
+            .iter()
+            .map(|(id, material)| (*id, material.mapping))
+            .collect()
+    }
+
+    /// Verifies that the textures of all materials exist.
+    pub fn validate(&self) -> Result<(), ResourceError> {
+        for material in self.materials.values() {
+            if let Some(ref texture) = material.texture {
+                if !texture.is_file() {
+                    return Err(ResourceError::TextureNotFound(
+                        texture.to_string_lossy().into_owned(),
+                    ));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+fn parse_material(
+    name: &str,
+    entries: &[(usize, String, String)],
+    base: &Path,
+) -> Result<Material, ResourceError> {
+    let mut material = Material::new(0, name);
+    let mut id = None;
+    for &(number, ref key, ref value) in entries {
+        let malformed = || ResourceError::Malformed(number);
+        match key.as_str() {
+            "id" => {
+                id = Some(value.parse::<ResourceId>().map_err(|_| malformed())?);
+            }
+            "texture" => {
+                material.texture = Some(base.join(value));
+            }
+            "tint" => {
+                let channels = value
+                    .split_whitespace()
+                    .map(|channel| channel.parse::<FScalar>())
+                    .collect::<Result<Vec<_>, _>>()
+                    .map_err(|_| malformed())?;
+                material.tint = match channels.len() {
+                    3 => Color::new(channels[0], channels[1], channels[2], 1.0),
+                    4 => Color::new(channels[0], channels[1], channels[2], channels[3]),
+                    _ => return Err(malformed()),
+                };
+            }
+            "projection" => {
+                material.mapping.projection = match value.as_str() {
+                    "world" => TextureProjection::World,
+                    "stretch" => TextureProjection::Stretch,
+                    "triplanar" => TextureProjection::Triplanar,
+                    _ => return Err(malformed()),
+                };
+            }
+            "rotation" => {
+                let degrees = value.parse::<FScalar>().map_err(|_| malformed())?;
+                material.mapping.rotation = degrees.to_radians();
+            }
+            "scale" => {
+                let scale = value.parse::<FScalar>().map_err(|_| malformed())?;
+                if !scale.is_finite() || scale <= 0.0 {
+                    return Err(malformed());
+                }
+                material.mapping.scale = scale;
+            }
+            "density" => {
+                material.physics.density = value.parse().map_err(|_| malformed())?;
+            }
+            "friction" => {
+                material.physics.friction = value.parse().map_err(|_| malformed())?;
+            }
+            "restitution" => {
+                material.physics.restitution = value.parse().map_err(|_| malformed())?;
+            }
+            _ => return Err(malformed()),
+        }
+    }
+    material.id = id.ok_or_else(|| ResourceError::MissingId(name.to_owned()))?;
+    Ok(material)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_manifest() {
+        let registry = Registry::from_manifest(
+            "# Materials.\n\
+             [stone]\n\
+             id = 0\n\
+             texture = texture/stone.png\n\
+             projection = triplanar\n\
+             \n\
+             [grass]\n\
+             id = 3\n\
+             tint = 0.5 1.0 0.5\n\
+             friction = 0.8\n",
+            "data",
+        ).unwrap();
+
+        assert_eq!(2, registry.len());
+        assert_eq!(Some(3), registry.id("grass"));
+        let stone = registry.get_by_name("stone").unwrap();
+        assert_eq!(
+            Some(Path::new("data/texture/stone.png")),
+            stone.texture.as_ref().map(|path| path.as_path())
+        );
+        assert_eq!(TextureProjection::Triplanar, stone.mapping.projection);
+        let grass = registry.get(3).unwrap();
+        assert!(grass.texture.is_none());
+        assert_eq!(0.8, grass.physics.friction);
+        // The texture of the stone material does not exist.
+        assert!(registry.validate().is_err());
+    }
+
+    #[test]
+    fn reject_duplicate_ids() {
+        let manifest = "[stone]\nid = 0\n[grass]\nid = 0\n";
+        assert!(Registry::from_manifest(manifest, "").is_err());
+    }
+
+    #[test]
+    fn reject_non_positive_scales() {
+        for scale in &["0", "-1.5", "NaN", "inf"] {
+            let manifest = format!("[stone]\nid = 0\nscale = {}\n", scale);
+            assert!(Registry::from_manifest(&manifest, "").is_err());
+        }
+        let manifest = "[stone]\nid = 0\nscale = 0.5\n";
+        let registry = Registry::from_manifest(manifest, "").unwrap();
+        assert_eq!(0.5, registry.get(0).unwrap().mapping.scale);
+    }
+}
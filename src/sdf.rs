@@ -0,0 +1,184 @@
+//! This module provides signed distance field (SDF) expressions.
+//!
+//! SDF expressions describe shapes as compositions of primitives and
+//! operations. They implement `DistanceField`, so they can be used to sculpt
+//! cubes in a tree or rasterized into a new tree. When rasterized, only cubes
+//! near the surface of the shape are subdivided.
+//!
+//! Primitives are centered at the origin; use `translate` to place them.
+//!
+//! # Examples
+//!
+//! Rasterizing a sphere with a hole through it:
+//!
+//! ```
+//! use bismuth::cube::LogWidth;
+//! use bismuth::math::FVector3;
+//! use bismuth::sdf::Sdf;
+//!
+//! let sdf = Sdf::sphere(64.0)
+//!     .subtract(Sdf::cuboid(&FVector3::new(96.0, 16.0, 16.0)))
+//!     .translate(&FVector3::new(128.0, 128.0, 128.0));
+//! let tree = sdf.rasterize(LogWidth::new(8), LogWidth::new(5), 0);
+//! ```
+
+use cube::{DistanceField, LogWidth, SculptMode, Tree};
+use math::{self, FPoint3, FScalar, FVector3};
+use resource::ResourceId;
+
+/// A signed distance field expression.
+#[derive(Clone)]
+pub enum Sdf {
+    /// A sphere with the given radius.
+    Sphere(FScalar),
+    /// A cuboid with the given half-extents.
+    Cuboid(FVector3),
+    /// A torus in the XZ plane with the given major and minor radii.
+    Torus(FScalar, FScalar),
+    /// Translates an expression.
+    Translate(Box<Sdf>, FVector3),
+    /// The union of two expressions.
+    Union(Box<Sdf>, Box<Sdf>),
+    /// The union of two expressions blended over the given distance.
+    SmoothUnion(Box<Sdf>, Box<Sdf>, FScalar),
+    /// The intersection of two expressions.
+    Intersection(Box<Sdf>, Box<Sdf>),
+    /// Subtracts the second expression from the first.
+    Subtract(Box<Sdf>, Box<Sdf>),
+    /// Repeats an expression infinitely with the given period along each
+    /// axis. A period of zero does not repeat along that axis.
+    Repeat(Box<Sdf>, FVector3),
+}
+
+impl Sdf {
+    pub fn sphere(radius: FScalar) -> Self {
+        Sdf::Sphere(radius)
+    }
+
+    pub fn cuboid(extent: &FVector3) -> Self {
+        Sdf::Cuboid(*extent)
+    }
+
+    pub fn torus(major: FScalar, minor: FScalar) -> Self {
+        Sdf::Torus(major, minor)
+    }
+
+    pub fn translate(self, translation: &FVector3) -> Self {
+        Sdf::Translate(Box::new(self), *translation)
+    }
+
+    pub fn union(self, other: Self) -> Self {
+        Sdf::Union(Box::new(self), Box::new(other))
+    }
+
+    pub fn smooth_union(self, other: Self, blend: FScalar) -> Self {
+        Sdf::SmoothUnion(Box::new(self), Box::new(other), blend)
+    }
+
+    pub fn intersection(self, other: Self) -> Self {
+        Sdf::Intersection(Box::new(self), Box::new(other))
+    }
+
+    pub fn subtract(self, other: Self) -> Self {
+        Sdf::Subtract(Box::new(self), Box::new(other))
+    }
+
+    pub fn repeat(self, period: &FVector3) -> Self {
+        Sdf::Repeat(Box::new(self), *period)
+    }
+
+    /// Rasterizes the expression into a new `Tree` with the given width.
+    ///
+    /// Cubes along the surface are subdivided to the given leaf width and
+    /// deformed to approximate the surface. Filled cubes are assigned the
+    /// given material.
+    pub fn rasterize(&self, width: LogWidth, leaf: LogWidth, material: ResourceId) -> Tree {
+        let mut tree = Tree::empty(width);
+        tree.as_cube_mut().sculpt(self, leaf, SculptMode::Add, material);
+        tree
+    }
+}
+
+impl DistanceField for Sdf {
+    fn distance(&self, point: &FPoint3) -> FScalar {
+        match *self {
+            Sdf::Sphere(radius) => point.coords.norm() - radius,
+            Sdf::Cuboid(ref extent) => {
+                let q = FVector3::new(
+                    point.x.abs() - extent.x,
+                    point.y.abs() - extent.y,
+                    point.z.abs() - extent.z,
+                );
+                let outside = FVector3::new(
+                    math::partial_max(q.x, 0.0),
+                    math::partial_max(q.y, 0.0),
+                    math::partial_max(q.z, 0.0),
+                ).norm();
+                let inside = math::partial_max(math::partial_max(q.x, q.y), q.z);
+                outside + math::partial_min(inside, 0.0)
+            }
+            Sdf::Torus(major, minor) => {
+                let radial = (point.x.powi(2) + point.z.powi(2)).sqrt() - major;
+                (radial.powi(2) + point.y.powi(2)).sqrt() - minor
+            }
+            Sdf::Translate(ref sdf, ref translation) => sdf.distance(&(*point - *translation)),
+            Sdf::Union(ref a, ref b) => math::partial_min(a.distance(point), b.distance(point)),
+            Sdf::SmoothUnion(ref a, ref b, blend) => {
+                let (a, b) = (a.distance(point), b.distance(point));
+                if blend <= 0.0 {
+                    math::partial_min(a, b)
+                }
+                else {
+                    // Polynomial smooth minimum. This never exceeds the
+                    // minimum of the operands, so distances are not
+                    // overestimated.
+                    let h = 0.5 + (0.5 * (b - a) / blend);
+                    let h = math::partial_min(math::partial_max(h, 0.0), 1.0);
+                    (b + ((a - b) * h)) - (blend * h * (1.0 - h))
+                }
+            }
+            Sdf::Intersection(ref a, ref b) => {
+                math::partial_max(a.distance(point), b.distance(point))
+            }
+            Sdf::Subtract(ref a, ref b) => {
+                math::partial_max(a.distance(point), -b.distance(point))
+            }
+            Sdf::Repeat(ref sdf, ref period) => {
+                let mut point = *point;
+                for axis in 0..3 {
+                    let period = period[axis];
+                    if period > 0.0 {
+                        let x = point[axis] + (period * 0.5);
+                        point[axis] = (x - (period * (x / period).floor())) - (period * 0.5);
+                    }
+                }
+                sdf.distance(&point)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use math::UPoint3;
+
+    #[test]
+    fn rasterize_sphere() {
+        let sdf = Sdf::sphere(64.0).translate(&FVector3::new(128.0, 128.0, 128.0));
+        let tree = sdf.rasterize(LogWidth::new(8), LogWidth::new(4), 0);
+
+        assert!(
+            !tree.as_cube()
+                .at_point(&UPoint3::new(128, 128, 128), LogWidth::new(4))
+                .unwrap()
+                .is_empty()
+        );
+        assert!(
+            tree.as_cube()
+                .at_point(&UPoint3::new(8, 8, 8), LogWidth::new(4))
+                .unwrap()
+                .is_empty()
+        );
+    }
+}
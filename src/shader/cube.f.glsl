@@ -2,11 +2,20 @@
 
 in vec4 v_color;
 in vec2 v_uv;
+in vec4 v_rect;
 
 out vec4 f_target0;
 
 uniform sampler2D t_texture;
 
 void main() {
-    f_target0 = texture(t_texture, vec2(v_uv.x, -v_uv.y)) * v_color;
+    // Wrap texture coordinates within the rectangle of the material in the
+    // atlas, so that textures tile. Gradients are taken from the unwrapped
+    // coordinates, because wrapping is discontinuous and would otherwise
+    // select the smallest mipmap along the seams between tiles.
+    vec2 unwrapped = vec2(v_uv.x, -v_uv.y);
+    vec2 uv = v_rect.xy + (fract(unwrapped) * v_rect.zw);
+    vec2 dx = dFdx(unwrapped) * v_rect.zw;
+    vec2 dy = dFdy(unwrapped) * v_rect.zw;
+    f_target0 = textureGrad(t_texture, uv, dx, dy) * v_color;
 }
@@ -3,9 +3,11 @@
 in vec3 a_position;
 in vec2 a_uv;
 in vec4 a_color;
+in vec4 a_rect;
 
 out vec4 v_color;
 out vec2 v_uv;
+out vec4 v_rect;
 
 uniform transform {
     mat4 u_camera;
@@ -15,5 +17,6 @@ uniform transform {
 void main() {
     v_color = a_color;
     v_uv = a_uv;
+    v_rect = a_rect;
     gl_Position = u_camera * u_model * vec4(a_position, 1.0);
 }
@@ -0,0 +1,300 @@
+//! This module provides procedural terrain generation.
+//!
+//! Terrain is generated from a density field built from layered noise. The
+//! surface of the terrain is where the density is zero, and cubes along the
+//! surface are deformed to follow it. Because the density field is seeded,
+//! terrain generated with the same seed and parameters is always the same.
+//!
+//! # Examples
+//!
+//! Generating terrain in a new tree:
+//!
+//! ```
+//! use bismuth::cube::LogWidth;
+//! use bismuth::terrain::Terrain;
+//!
+//! let terrain = Terrain::new(42);
+//! let tree = terrain.generate(LogWidth::new(8), LogWidth::new(5));
+//! ```
+
+mod noise;
+
+use num::Bounded;
+use std::collections::BTreeMap;
+
+use cube::{Cube, DistanceField, LogWidth, Node, Partition, SculptMode, Spatial, Tree};
+use math::{FPoint3, FScalar, FVector3, IntoSpace, UScalar};
+use resource::ResourceId;
+
+pub use self::noise::Perlin;
+
+/// A layer of material beneath the surface of terrain.
+#[derive(Clone, Copy)]
+pub struct Stratum {
+    /// The depth beneath the surface at which the `Stratum` ends.
+    pub depth: FScalar,
+    pub material: ResourceId,
+}
+
+impl Stratum {
+    pub fn new(depth: FScalar, material: ResourceId) -> Self {
+        Stratum {
+            depth: depth,
+            material: material,
+        }
+    }
+}
+
+/// Parameters for generating terrain.
+///
+/// Distances are in the same units as the tree, and frequencies are the
+/// number of noise features per unit.
+pub struct Terrain {
+    /// Seed used to generate noise.
+    pub seed: u32,
+    /// Height of the mean surface of the terrain.
+    pub height: FScalar,
+    /// Maximum vertical displacement of the surface by hills and valleys.
+    pub amplitude: FScalar,
+    /// Frequency of hills and valleys.
+    pub frequency: FScalar,
+    /// Number of layers of noise used to form hills and valleys.
+    pub octaves: usize,
+    /// Maximum displacement of the surface by three-dimensional noise, which
+    /// forms overhangs and cliffs.
+    pub overhang: FScalar,
+    /// Frequency of caves.
+    pub cave_frequency: FScalar,
+    /// Width of the tunnels that form caves, as a fraction of the range of the
+    /// cave noise. Zero disables caves.
+    pub cave_width: FScalar,
+    /// Materials assigned by depth beneath the surface, ordered from the
+    /// surface downward. Cubes deeper than the last `Stratum` are assigned
+    /// its material.
+    pub strata: Vec<Stratum>,
+}
+
+impl Terrain {
+    pub fn new(seed: u32) -> Self {
+        Terrain {
+            seed: seed,
+            height: 96.0,
+            amplitude: 48.0,
+            frequency: 1.0 / 128.0,
+            octaves: 4,
+            overhang: 16.0,
+            cave_frequency: 1.0 / 64.0,
+            cave_width: 0.08,
+            strata: vec![
+                Stratum::new(8.0, 1),
+                Stratum::new(48.0, 2),
+                Stratum::new(FScalar::max_value(), 3),
+            ],
+        }
+    }
+
+    /// Generates terrain in a new `Tree` with the given width. Cubes along
+    /// the surface are subdivided to the given leaf width.
+    pub fn generate(&self, width: LogWidth, leaf: LogWidth) -> Tree {
+        let mut tree = Tree::empty(width);
+        self.fill(&mut tree, leaf);
+        tree
+    }
+
+    /// Adds terrain to an existing `Tree`. Cubes along the surface are
+    /// subdivided to the given leaf width.
+    ///
+    /// Materials are only assigned to the cubes filled by the terrain. Cubes
+    /// that were occupied before the terrain was added keep their material.
+    pub fn fill(&self, tree: &mut Tree, leaf: LogWidth) {
+        let field = DensityField::new(self);
+        // Only occupied leaves that the terrain may fill can lose their
+        // material, so only those are recorded before sculpting.
+        let mut occupied = BTreeMap::new();
+        record_occupied(&tree.as_cube(), &field, &mut occupied);
+        let width = tree.as_cube().partition().width();
+        let partitions = tree.as_cube_mut().sculpt(&field, leaf, SculptMode::Add, 0);
+        let mut root = tree.as_cube_mut();
+        for partition in partitions {
+            if let Some(mut cube) = root.at_point_mut(partition.origin(), partition.width()) {
+                for mut cube in cube.iter_mut() {
+                    let midpoint = cube.partition().midpoint();
+                    let material = (LogWidth::min_value().to_inner()..(width.to_inner() + 1))
+                        .filter_map(|width| {
+                            let partition = Partition::at_point(&midpoint, LogWidth::new(width));
+                            occupied.get(&key(&partition)).cloned()
+                        })
+                        .next();
+                    let center: FPoint3 = midpoint.into_space();
+                    if let Some(leaf) = cube.as_leaf_mut() {
+                        if !leaf.geometry.is_empty() {
+                            leaf.material = material
+                                .unwrap_or_else(|| self.material(field.density(&center)));
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Gets the material at a given depth beneath the surface.
+    fn material(&self, depth: FScalar) -> ResourceId {
+        self.strata
+            .iter()
+            .find(|stratum| depth <= stratum.depth)
+            .or_else(|| self.strata.last())
+            .map_or(0, |stratum| stratum.material)
+    }
+}
+
+/// The density field of `Terrain`.
+///
+/// Density is approximately the depth beneath the surface, so it is positive
+/// inside of the terrain and negative outside of it.
+struct DensityField<'a> {
+    terrain: &'a Terrain,
+    surface: Perlin,
+    overhang: Perlin,
+    caves: Perlin,
+    /// An upper bound on the rate of change of the density, used to scale
+    /// density into a conservative distance.
+    lipschitz: FScalar,
+}
+
+impl<'a> DensityField<'a> {
+    fn new(terrain: &'a Terrain) -> Self {
+        // The derivative of Perlin noise is bounded by a small constant, and
+        // each octave of fractal noise contributes proportionally to its
+        // frequency and amplitude.
+        let gradient = 2.5;
+        let surface = terrain.amplitude * terrain.frequency * gradient * terrain.octaves as FScalar;
+        let overhang = terrain.overhang * terrain.frequency * 4.0 * gradient;
+        DensityField {
+            terrain: terrain,
+            surface: Perlin::new(terrain.seed),
+            overhang: Perlin::new(terrain.seed.wrapping_add(1)),
+            caves: Perlin::new(terrain.seed.wrapping_add(2)),
+            lipschitz: 1.0 + surface + overhang,
+        }
+    }
+
+    fn density(&self, point: &FPoint3) -> FScalar {
+        let terrain = self.terrain;
+        let planar = FPoint3::new(point.x, 0.0, point.z) * terrain.frequency;
+        let spatial = *point * (terrain.frequency * 4.0);
+        let surface = self.surface.fractal(&planar, terrain.octaves) * terrain.amplitude;
+        let overhang = self.overhang.sample(&spatial) * terrain.overhang;
+        let density = (terrain.height + surface - point.y) + overhang;
+        if terrain.cave_width > 0.0 {
+            // Caves are tunnels along the zero set of the cave noise. Scale the
+            // cave density such that its rate of change is comparable to the
+            // rate of change of the terrain density.
+            let scale = 1.0 / (terrain.cave_frequency * 2.5);
+            let cave = self.caves.sample(&(*point * terrain.cave_frequency));
+            let cave = (cave.abs() - terrain.cave_width) * scale;
+            density.min(cave)
+        }
+        else {
+            density
+        }
+    }
+}
+
+impl<'a> DistanceField for DensityField<'a> {
+    fn distance(&self, point: &FPoint3) -> FScalar {
+        -self.density(point) / self.lipschitz
+    }
+}
+
+/// Identifies a `Partition` by its origin and width.
+type PartitionKey = (UScalar, UScalar, UScalar, u8);
+
+fn key(partition: &Partition) -> PartitionKey {
+    let origin = partition.origin();
+    (origin.x, origin.y, origin.z, partition.width().to_inner())
+}
+
+/// Records the materials of the occupied leaves of a cube that may be filled
+/// by a `DistanceField` when adding, keyed by their `Partition`s.
+///
+/// Like sculpting, this skips cubes that lie entirely outside of the surface.
+fn record_occupied<F>(
+    cube: &Cube<&Node>,
+    field: &F,
+    occupied: &mut BTreeMap<PartitionKey, ResourceId>,
+) where
+    F: DistanceField,
+{
+    let partition = cube.partition();
+    let origin: FPoint3 = (*partition.origin()).into_space();
+    let extent = partition.width().exp() as FScalar;
+    let radius = extent * (3.0 as FScalar).sqrt() * 0.5;
+    let center = origin + (FVector3::new(1.0, 1.0, 1.0) * (extent * 0.5));
+    if field.distance(&center) > radius {
+        return;
+    }
+    if let Some(leaf) = cube.as_leaf() {
+        if !leaf.geometry.is_empty() {
+            occupied.insert(key(partition), leaf.material);
+        }
+    }
+    else {
+        for index in 0..8 {
+            if let Some(cube) = cube.at_index(index) {
+                record_occupied(&cube, field, occupied);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cube::Geometry;
+    use math::UPoint3;
+
+    fn leaves(tree: &Tree) -> Vec<(UPoint3, Geometry, ResourceId)> {
+        tree.as_cube()
+            .iter()
+            .filter_map(|cube| {
+                let origin = *cube.partition().origin();
+                cube.as_leaf()
+                    .map(|leaf| (origin, leaf.geometry, leaf.material))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn generate_is_deterministic() {
+        let terrain = Terrain::new(7);
+        let a = terrain.generate(LogWidth::new(8), LogWidth::new(6));
+        let b = terrain.generate(LogWidth::new(8), LogWidth::new(6));
+
+        assert!(leaves(&a) == leaves(&b));
+    }
+
+    #[test]
+    fn fill_preserves_existing_materials() {
+        let mut tree = Tree::empty(LogWidth::new(8));
+        let _ = tree.as_cube_mut().subdivide();
+        if let Some(mut cube) = tree.as_cube_mut().at_index_mut(0) {
+            if let Some(leaf) = cube.as_leaf_mut() {
+                leaf.geometry = Geometry::full();
+                leaf.material = 9;
+            }
+        }
+        Terrain::new(7).fill(&mut tree, LogWidth::new(6));
+
+        let materials = leaves(&tree)
+            .into_iter()
+            .filter(|&(origin, ref geometry, _)| {
+                origin.x < 128 && origin.y < 128 && origin.z < 128 && !geometry.is_empty()
+            })
+            .map(|(_, _, material)| material)
+            .collect::<Vec<_>>();
+        assert!(!materials.is_empty());
+        assert!(materials.iter().all(|material| *material == 9));
+        // Cubes filled by the terrain are assigned the materials of strata.
+        assert!(leaves(&tree).iter().any(|&(_, _, material)| material != 9));
+    }
+}
@@ -0,0 +1,121 @@
+use rand::{Rng, SeedableRng, XorShiftRng};
+
+use math::{FPoint3, FScalar};
+
+/// Seeded three-dimensional gradient (Perlin) noise.
+///
+/// The same seed always produces the same noise, so noise can be used to
+/// generate reproducible content.
+pub struct Perlin {
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u32) -> Self {
+        // `XorShiftRng` cannot be seeded with zeroes, so mix the seed with
+        // non-zero constants.
+        let mut rng = XorShiftRng::from_seed([
+            seed ^ 0x9E37_79B9,
+            seed.rotate_left(16) ^ 0x85EB_CA6B,
+            0xC2B2_AE35,
+            0x27D4_EB2F,
+        ]);
+        let mut permutation = (0..256).collect::<Vec<_>>();
+        rng.shuffle(&mut permutation);
+        let repetition = permutation.clone();
+        permutation.extend(repetition);
+        Perlin {
+            permutation: permutation,
+        }
+    }
+
+    /// Samples the noise at a point. Noise is approximately in the range
+    /// `[-1, 1]` and is zero at integer lattice points.
+    pub fn sample(&self, point: &FPoint3) -> FScalar {
+        let (x, y, z) = (point.x.floor(), point.y.floor(), point.z.floor());
+        let (i, j, k) = (wrap(x), wrap(y), wrap(z));
+        let (x, y, z) = (point.x - x, point.y - y, point.z - z);
+        let (u, v, w) = (fade(x), fade(y), fade(z));
+
+        let p = &self.permutation;
+        let a = p[i] + j;
+        let (aa, ab) = (p[a] + k, p[a + 1] + k);
+        let b = p[i + 1] + j;
+        let (ba, bb) = (p[b] + k, p[b + 1] + k);
+
+        lerp(
+            w,
+            lerp(
+                v,
+                lerp(u, gradient(p[aa], x, y, z), gradient(p[ba], x - 1.0, y, z)),
+                lerp(
+                    u,
+                    gradient(p[ab], x, y - 1.0, z),
+                    gradient(p[bb], x - 1.0, y - 1.0, z),
+                ),
+            ),
+            lerp(
+                v,
+                lerp(
+                    u,
+                    gradient(p[aa + 1], x, y, z - 1.0),
+                    gradient(p[ba + 1], x - 1.0, y, z - 1.0),
+                ),
+                lerp(
+                    u,
+                    gradient(p[ab + 1], x, y - 1.0, z - 1.0),
+                    gradient(p[bb + 1], x - 1.0, y - 1.0, z - 1.0),
+                ),
+            ),
+        )
+    }
+
+    /// Samples layered (fractal) noise at a point. Each octave doubles the
+    /// frequency and halves the amplitude of the previous octave. The result
+    /// is normalized to approximately the range `[-1, 1]`.
+    pub fn fractal(&self, point: &FPoint3, octaves: usize) -> FScalar {
+        let mut sum = 0.0;
+        let mut amplitude = 1.0;
+        let mut frequency = 1.0;
+        let mut total = 0.0;
+        for _ in 0..octaves {
+            sum += self.sample(&(*point * frequency)) * amplitude;
+            total += amplitude;
+            amplitude *= 0.5;
+            frequency *= 2.0;
+        }
+        if total > 0.0 {
+            sum / total
+        }
+        else {
+            0.0
+        }
+    }
+}
+
+fn wrap(x: FScalar) -> usize {
+    ((x as i64) & 0xFF) as usize
+}
+
+fn fade(t: FScalar) -> FScalar {
+    t * t * t * ((t * ((t * 6.0) - 15.0)) + 10.0)
+}
+
+fn lerp(t: FScalar, a: FScalar, b: FScalar) -> FScalar {
+    a + (t * (b - a))
+}
+
+fn gradient(hash: usize, x: FScalar, y: FScalar, z: FScalar) -> FScalar {
+    let h = hash & 0xF;
+    let u = if h < 8 { x } else { y };
+    let v = if h < 4 {
+        y
+    }
+    else if h == 12 || h == 14 {
+        x
+    }
+    else {
+        z
+    };
+    (if h & 1 == 0 { u } else { -u }) + (if h & 2 == 0 { v } else { -v })
+}